@@ -1,12 +1,13 @@
 //! Top-level application code, state management, and program control.
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     fmt::{self, Debug, Formatter},
-    fs::File,
+    fs::{self, File},
     hash::{Hash, Hasher},
-    io::Read,
+    io::{self, Read},
     iter::Iterator,
+    mem,
     path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -15,20 +16,38 @@ use std::{
 };
 
 use ansi_term::{Color, Style};
-use clap::{App, Arg, ArgMatches, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, Error as ClapError, ErrorKind, SubCommand, Values};
 use crossbeam_channel::Sender;
 use git2::Repository;
 use ini::Ini;
+use termion;
 use users::{self, os::unix::UserExt};
 use walkdir::WalkDir;
 
+use render::{Format, FORMATS};
+use theme::Theme;
+use ui::to_ascii;
+
 /// Name of the program (`mgit`).
 const NAME: &str = "mgit";
 /// One-line description of the program.
 const ABOUT: &str = "Small program for managing multiple git repositories.";
 
+/// Keys that cannot be used in the `[mgit]` sections (see `defaults()`).
+const RESERVED_SETTINGS: [&str; 4] = ["config", "help", "profile", "version"];
+
+/// Name for the `--ascii` argument.
+const ASCII_ARG: &str = "ASCII";
+/// Name for the `--no-ascii` argument.
+const NO_ASCII_ARG: &str = "NO_ASCII";
+/// Name for the `--color` argument.
+const COLOR_ARG: &str = "COLOR";
 /// Name for the `-c/--config` argument.
 const CONFIG_ARG: &str = "CONFIG";
+/// Name for the `--format` argument.
+const FORMAT_ARG: &str = "FORMAT";
+/// Name for the `-p/--profile` argument.
+const PROFILE_ARG: &str = "PROFILE";
 /// Name for the `-W/--warning` argument.
 const WARNING_ARG: &str = "WARNING";
 
@@ -41,30 +60,264 @@ pub fn init<'a>(
     exit: fn(i32),
     commands: &'a [Command<'a>],
 ) -> Invocation<'a> {
-    // Configure the top-level app instance.
-    let mut app = App::new(NAME)
+    // Parse the input from the user.
+    let matches = app(commands).get_matches();
+
+    // Config paths come from `-c/--config`, `MGIT_CONFIG` or the default locations,
+    // in layers (see `config_layers()`).
+    let mut layers = match matches.values_of(CONFIG_ARG) {
+        Some(paths) => vec![paths.map(|path| path.to_owned()).collect()],
+        None => config_layers(),
+    };
+    let found_config = !layers.is_empty();
+
+    // Profiles are defined in the configuration at the config paths, and may replace
+    // those paths with their own, so the profile has to be resolved before the
+    // configuration is read. Errors reading the files are ignored here, since
+    // they're reported when the configuration is read (unless the profile replaces
+    // the paths, in which case they don't matter). The warning action (and colors)
+    // may be set in the configuration itself, so errors are held on to until we know
+    // what to do with them.
+    let mut errors = Vec::new();
+    let profile_name = match matches.value_of(PROFILE_ARG) {
+        Some("") | None => None,
+        Some(name) => Some(name),
+    };
+    let mut profile = None;
+    if let Some(name) = profile_name {
+        let mut files = Vec::new();
+        for layer in &layers {
+            let mut seen = Vec::new();
+            for path in layer {
+                files.append(&mut read_files(path, &mut seen).0);
+            }
+        }
+        let (p, mut profile_errors) = read_profile(name, &files);
+        errors.append(&mut profile_errors);
+        profile = p;
+    }
+    if let Some(ref profile) = profile {
+        if !profile.config_paths().is_empty() {
+            layers = vec![profile.config_paths()];
+        }
+    }
+
+    // Read the configuration, then add the settings from the profile on top.
+    let mut config = Config::new();
+    for layer in &layers {
+        config.next_layer();
+        for path in layer {
+            errors.append(&mut config.read(path));
+        }
+    }
+    let unknown_profile = match (profile_name, &profile) {
+        (Some(name), &None) => Some(name),
+        _ => None,
+    };
+    if let Some(profile) = profile {
+        config.add_profile(profile);
+    }
+
+    // Defaults from the `[mgit]` sections of the configuration fill in for arguments
+    // the user did not supply.
+    let (defaults, mut settings_errors) = defaults(commands, &config, matches.subcommand_name());
+    errors.append(&mut settings_errors);
+    let value_of = |name: &str| {
+        if matches.occurrences_of(name) == 0 && defaults.occurrences_of(name) > 0 {
+            defaults.value_of(name)
+        } else {
+            matches.value_of(name)
+        }
+    };
+
+    // Get the argument values.
+    let color_when = value_of(COLOR_ARG).expect("no value for color argument");
+    let color = match color_when {
+        "auto" => ColorWhen::Auto,
+        "always" => ColorWhen::Always,
+        "never" => ColorWhen::Never,
+        &_ => panic!("unexpected value for color ('{}')", color_when),
+    };
+    // The command line beats the configuration, which beats the locale.
+    let ascii = if matches.is_present(ASCII_ARG) {
+        true
+    } else if matches.is_present(NO_ASCII_ARG) {
+        false
+    } else {
+        defaults.is_present(ASCII_ARG) || !locale_is_utf8()
+    };
+    // Actions for warnings accumulate, with the command line applied on top of the
+    // configuration.
+    let mut warning_policy = WarningPolicy::new();
+    for m in &[&defaults, &matches] {
+        if m.occurrences_of(WARNING_ARG) > 0 {
+            for value in m
+                .values_of(WARNING_ARG)
+                .expect("no value for warning argument")
+            {
+                warning_policy.set(value).unwrap_or_else(|e| {
+                    panic!("unexpected value for warning argument ({})", e.message())
+                });
+            }
+        }
+    }
+    let format_name = value_of(FORMAT_ARG).expect("no value for format argument");
+    let format = Format::from_name(format_name)
+        .unwrap_or_else(|| panic!("unexpected value for format ('{}')", format_name));
+
+    // Control instance for the invocation.
+    let control = Control::new(
+        exit,
+        warning_policy,
+        color.enabled(termion::is_tty(&io::stderr())),
+        ascii,
+    );
+
+    // Pass errors from reading the configuration to the control instance, as
+    // warnings.
+    for error in &errors {
+        control.config_warning(error);
+    }
+
+    // Repos waiting to be cloned are not an error, but should not go unnoticed either
+    // (except by the commands that deal with them, see `Command.warn_uncloned`).
+    let warn_uncloned = commands
+        .iter()
+        .filter(|command| matches.subcommand_name() == Some(command.name))
+        .all(|command| command.warn_uncloned);
+    if warn_uncloned {
+        for repo in config.uncloned() {
+            control.config_warning(
+                &ConfigError::new(
+                    Category::MissingPath,
+                    repo.config_path(),
+                    Some(repo.path()),
+                    "repo is not cloned yet",
+                    Some(&format!(
+                        "run `mgit clone` to clone it from {}",
+                        repo.url().expect("expected uncloned repo to have a url")
+                    )),
+                )
+                .at(repo.position()),
+            );
+        }
+    }
+
+    if let Some(name) = unknown_profile {
+        control.fatal(&format!(
+            "unknown profile '{}' (expected a [{}{}] section in the configuration)",
+            name, PROFILE_SECTION_PREFIX, name
+        ));
+    }
+
+    // Check that we actually got some repos. If not, something likely went seriously
+    // wrong somewhere. In any case, mgit can't do anything useful.
+    if !found_config {
+        control.fatal("no configuration found (see `mgit -h` for -c/--config, or set MGIT_CONFIG)");
+    }
+    if config.repos().len() == 0 && config.uncloned().is_empty() {
+        control.fatal("no repositories configured");
+    }
+
+    // Determine which (if any) subcommand the user invoked, then return it and a
+    // newly-created invocation instance to the caller.
+    for command in commands {
+        if let Some(m) = matches.subcommand_matches(command.name) {
+            let color = color.enabled(termion::is_tty(&io::stdout()));
+            let empty = ArgMatches::new();
+            let d = defaults.subcommand_matches(command.name).unwrap_or(&empty);
+            return Invocation::new(
+                sigterm_arc,
+                control,
+                config,
+                format,
+                color,
+                ascii,
+                command,
+                m,
+                d,
+            );
+        }
+    }
+
+    control.fatal("no command supplied, see `mgit -h` for usage info");
+    panic!("unreachable");
+}
+
+// ----- app ------------------------------------------------------------------
+
+/// Returns the top-level clap app, with `commands` attached as subcommands.
+fn app<'a>(commands: &'a [Command<'a>]) -> App<'a, 'a> {
+    let mut rv = App::new(NAME)
         .version(crate_version!())
         .author(crate_authors!())
         .about(ABOUT)
+        .arg(
+            Arg::with_name(ASCII_ARG)
+                .help("Uses only ASCII characters in output (default if the locale is not UTF-8)")
+                .long("ascii"),
+        )
+        .arg(negation(NO_ASCII_ARG, "no-ascii", ASCII_ARG))
+        .arg(
+            Arg::with_name(COLOR_ARG)
+                .default_value("auto")
+                .help("When to use colors in output")
+                .long("color")
+                .possible_values(&["auto", "always", "never"])
+                .takes_value(true)
+                .value_name("WHEN"),
+        )
         .arg(
             Arg::with_name(CONFIG_ARG)
-                .default_value("~/.mgit")
-                .help("Path to configuration file or directory")
+                .help(
+                    "Path to configuration file or directory [default: $MGIT_CONFIG, \
+                     or the XDG config directories, ~/.mgit and .mgit in the current \
+                     directory or above]",
+                )
                 .short("c")
                 .long("config")
                 .multiple(true)
                 .number_of_values(1)
                 .value_name("PATH"),
         )
+        .arg(
+            Arg::with_name(FORMAT_ARG)
+                .default_value("human")
+                .help("Output format")
+                .long("format")
+                .possible_values(&FORMATS)
+                .takes_value(true)
+                .value_name("FORMAT"),
+        )
+        .arg(
+            Arg::with_name(PROFILE_ARG)
+                .env("MGIT_PROFILE")
+                .help("Uses the named profile from the configuration")
+                .short("p")
+                .long("profile")
+                .takes_value(true)
+                .value_name("NAME"),
+        )
         .arg(
             Arg::with_name(WARNING_ARG)
                 .default_value("print")
-                .help("Action to take on warnings")
+                .help(
+                    "Action to take on warnings (ignore, print or fatal), or on one category \
+                     of warnings as CATEGORY=ACTION (categories: unreadable, parse-error, \
+                     include, missing-path, not-a-repo, duplicate, setting, theme, events, \
+                     lint)",
+                )
                 .short("W")
                 .long("warning")
-                .possible_values(&["ignore", "print", "fatal"])
+                .multiple(true)
+                .number_of_values(1)
                 .takes_value(true)
-                .value_name("ACTION"),
+                .validator(|value| {
+                    WarningPolicy::new()
+                        .set(&value)
+                        .map_err(|e| e.message().to_owned())
+                })
+                .value_name("[CATEGORY=]ACTION"),
         );
 
     // Attach each of the subcommands and their arguments.
@@ -73,70 +326,362 @@ pub fn init<'a>(
         for arg in command.args() {
             subcommand = subcommand.arg(arg);
         }
-        app = app.subcommand(subcommand);
+        rv = rv.subcommand(subcommand);
     }
+    rv
+}
 
-    // Parse the input from the user.
-    let matches = app.get_matches();
+// ----- defaults -------------------------------------------------------------
 
-    // Get the argument values.
-    let config_paths = matches
-        .values_of(CONFIG_ARG)
-        .expect("no value for config argument");
-    let warning_action = matches
-        .value_of(WARNING_ARG)
-        .expect("no value for warning action argument");
+/// Returns the default arguments from the `[mgit]` and `[mgit.<command>]` sections
+/// of `config`, as the `ArgMatches` they would produce on the command line, along
+/// with a list of errors for settings that are invalid.
+///
+/// Each setting is checked by handing it to clap, so the rules for values are the
+/// same as on the command line. Settings in `[mgit]` apply to the top-level
+/// arguments and to the arguments of any command that has one by that name;
+/// settings in `[mgit.<command>]` apply only to `command` and take precedence over
+/// `[mgit]`. Settings from a profile take precedence over both (and are ranked
+/// among themselves the same way). Only the settings for the invoked `command` (if
+/// any) end up in the returned matches.
+fn defaults<'a>(
+    commands: &'a [Command<'a>],
+    config: &Config,
+    command: Option<&str>,
+) -> (ArgMatches<'a>, Vec<ConfigError>) {
+    let mut errors = Vec::new();
+    let mut top_args = BTreeMap::new();
+    let mut command_args = BTreeMap::new();
+    for setting in config.settings() {
+        let rank = (setting.profile().is_some(), setting.command().is_some());
+        let error = |message: &str, cause: Option<&str>| {
+            ConfigError::new(
+                Category::Setting,
+                setting.config_path(),
+                None,
+                message,
+                cause,
+            )
+            .at(setting.position())
+        };
+        let name = match setting.profile() {
+            Some(profile) => format!("{} in profile {}", setting, profile),
+            None => format!("{}", setting),
+        };
 
-    // Control instance for the invocation.
-    let control = Control::new(
-        exit,
-        match warning_action {
-            "ignore" => Action::Ignore,
-            "print" => Action::Print,
-            "fatal" => Action::Fatal,
-            &_ => panic!("unexpected value for warning action ('{}')", warning_action),
-        },
-    );
+        // By the time settings are read, the configuration has already been read, so
+        // config paths can't be set here. `--help` and `--version` make no sense as
+        // defaults.
+        if RESERVED_SETTINGS.contains(&setting.key()) {
+            errors.push(error(
+                &format!("invalid setting {}", name),
+                Some("this argument cannot be set in the configuration"),
+            ));
+            continue;
+        }
+        // Negations only make sense against the configuration, so setting one in it
+        // would do nothing.
+        if setting.key().starts_with(NEGATION_PREFIX) {
+            errors.push(error(
+                &format!("invalid setting {}", name),
+                Some("negations can only be given on the command line (set the flag to false instead)"),
+            ));
+            continue;
+        }
 
-    // Read the configuration from the provided `-c/--config` paths, passing errors
-    // from the config reader to the control instance, as warnings.
-    let mut config = Config::new();
-    for path in config_paths {
-        for error in config.read(path) {
-            let mut s = format!("{}", Style::new().bold().paint(error.message()));
-            if let Some(cause) = error.cause() {
-                s.push_str(&format!("\n{}", cause));
+        let targets = match setting.command() {
+            Some(name) => match commands.iter().find(|command| command.name == name) {
+                Some(command) => vec![Some(command.name)],
+                None => {
+                    errors.push(error(
+                        &format!("unknown section [mgit.{}] (ignoring)", name),
+                        Some("expected the name of a command"),
+                    ));
+                    continue;
+                }
+            },
+            None => {
+                let mut targets = vec![None];
+                targets.extend(commands.iter().map(|command| Some(command.name)));
+                targets
             }
-            s.push_str(&format!(
-                "\nin config at path {}",
-                Color::Cyan.bold().paint(error.config_path())
-            ));
-            if let Some(repo_path) = error.repo_path() {
-                s.push_str(&format!(
-                    "\nfor repo  at path {}",
-                    Color::Blue.bold().paint(repo_path)
-                ));
+        };
+
+        let mut applies = false;
+        let mut cause = None;
+        for target in targets {
+            match setting_args(commands, target, setting) {
+                Ok(args) => {
+                    applies = true;
+                    let args_map = match target {
+                        None => &mut top_args,
+                        Some(target) if Some(target) == command => &mut command_args,
+                        Some(_) => continue,
+                    };
+                    let outranked = match args_map.get(setting.key()) {
+                        Some(&(other_rank, _)) => other_rank > rank,
+                        None => false,
+                    };
+                    if !outranked {
+                        args_map.insert(setting.key(), (rank, args));
+                    }
+                }
+                Err(e) => {
+                    // Only an error about the argument itself means it doesn't exist
+                    // for `target`; anything else is about the value.
+                    let flag = format!("--{}", setting.key());
+                    if e.kind != ErrorKind::UnknownArgument || e.info != Some(vec![flag]) {
+                        cause = Some(clap_message(&e));
+                    }
+                }
             }
-            control.warning(&s);
+        }
+        if !applies {
+            errors.push(match cause {
+                Some(cause) => error(&format!("invalid setting {}", name), Some(&cause)),
+                None => error(&format!("unknown setting {}", name), None),
+            });
         }
     }
 
-    // Check that we actually got some repos. If not, something likely went seriously
-    // wrong somewhere. In any case, mgit can't do anything useful.
-    if config.repos().len() == 0 {
-        control.fatal("no repositories configured");
+    let mut argv = vec![String::from(NAME)];
+    for (_, args) in top_args.values() {
+        argv.extend(args.iter().cloned());
+    }
+    if let Some(command) = command {
+        argv.push(command.to_owned());
+        for (_, args) in command_args.values() {
+            argv.extend(args.iter().cloned());
+        }
     }
+    let defaults = app(commands)
+        .setting(AppSettings::ColorNever)
+        .get_matches_from_safe(argv)
+        .unwrap_or_else(|e| {
+            // Settings are checked one at a time, so this only happens when they don't
+            // work together (e.g. when one requires another that is not set).
+            let mut paths = config
+                .settings()
+                .iter()
+                .map(|setting| setting.config_path())
+                .collect::<Vec<&str>>();
+            paths.dedup();
+            errors.push(ConfigError::new(
+                Category::Setting,
+                &paths.join(", "),
+                None,
+                "invalid combination of settings (ignoring all settings)",
+                Some(&clap_message(&e)),
+            ));
+            ArgMatches::new()
+        });
+    (defaults, errors)
+}
 
-    // Determine which (if any) subcommand the user invoked, then return it and a
-    // newly-created invocation instance to the caller.
-    for command in commands {
-        if let Some(m) = matches.subcommand_matches(command.name) {
-            return Invocation::new(sigterm_arc, control, config, command, m);
+// ----- setting_args ---------------------------------------------------------
+
+/// Returns the command line arguments equivalent to `setting`, for the top-level
+/// app (if `command` is `None`) or for `command`, or the clap error if they are
+/// not valid there.
+///
+/// The key is the long name of the argument. Values with whitespace are tried as
+/// multiple values first (e.g. `tag = work rust`), then as a single value. Flags
+/// take `true` or `false` to turn them on or off.
+fn setting_args<'a>(
+    commands: &'a [Command<'a>],
+    command: Option<&str>,
+    setting: &Setting,
+) -> Result<Vec<String>, ClapError> {
+    let (key, value) = (setting.key(), setting.value());
+    let flag = format!("--{}", key);
+    let parse = |args: Vec<String>| {
+        let mut argv = vec![String::from(NAME)];
+        if let Some(command) = command {
+            argv.push(command.to_owned());
         }
+        argv.extend(args);
+        match app(commands)
+            .setting(AppSettings::ColorNever)
+            .get_matches_from_safe(argv)
+        {
+            // Arguments that require others are checked on their own here, and then
+            // again with everything else in `defaults()`.
+            Err(ref e) if e.kind == ErrorKind::MissingRequiredArgument => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        }
+    };
+
+    let mut candidates = Vec::new();
+    if value.split_whitespace().count() > 1 {
+        let mut args = Vec::new();
+        for word in value.split_whitespace() {
+            args.push(flag.clone());
+            args.push(word.to_owned());
+        }
+        candidates.push(args);
     }
+    candidates.push(vec![flag.clone(), value.to_owned()]);
 
-    control.fatal("no command supplied, see `mgit -h` for usage info");
-    panic!("unreachable");
+    let mut rv = None;
+    for args in candidates {
+        match parse(args.clone()) {
+            Ok(()) => return Ok(args),
+            Err(e) => rv = Some(e),
+        }
+    }
+    let e = rv.expect("failed to check setting");
+
+    // If clap took the value as an argument of its own, the argument doesn't take a
+    // value, so it's a flag.
+    let is_flag = (e.kind == ErrorKind::UnknownArgument
+        || e.kind == ErrorKind::UnrecognizedSubcommand)
+        && e.info == Some(vec![value.to_owned()])
+        && parse(vec![flag.clone()]).is_ok();
+    if !is_flag {
+        return Err(e);
+    }
+    match value {
+        "true" => Ok(vec![flag]),
+        "false" => Ok(vec![]),
+        _ => Err(ClapError::with_description(
+            &format!("expected true or false (got '{}')", value),
+            ErrorKind::InvalidValue,
+        )),
+    }
+}
+
+// ----- negation -------------------------------------------------------------
+
+/// Prefix of the long names of negations (see `negation()`).
+const NEGATION_PREFIX: &str = "no-";
+
+/// Returns the (hidden) argument named `name`, with the long name `long`, that
+/// turns off the flag named `flag` when it is set to `true` in the configuration.
+/// Whichever of the two is given last on the command line wins.
+///
+/// `name` must be `flag` prefixed with `NO_`, and `long` the long name of `flag`
+/// prefixed with `no-` (see `Invocation::matches_for()`).
+pub fn negation<'a>(name: &'a str, long: &'a str, flag: &'a str) -> Arg<'a, 'a> {
+    debug_assert_eq!(name, negation_name(flag));
+    debug_assert!(long.starts_with(NEGATION_PREFIX));
+    Arg::with_name(name)
+        .help("Turns off the flag, if it is set in the configuration")
+        .hidden(true)
+        .long(long)
+        .overrides_with(flag)
+}
+
+/// Returns the name of the negation of the flag named `flag` (see `negation()`).
+fn negation_name(flag: &str) -> String {
+    format!("NO_{}", flag)
+}
+
+// ----- clap_message ---------------------------------------------------------
+
+/// Returns the message from the clap error `e`, without the `error: ` prefix or
+/// the usage information.
+fn clap_message(e: &ClapError) -> String {
+    // Messages from clap always start with the prefix, and the usage information is
+    // separated from the message by an empty line.
+    e.message
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .flat_map(|line| line.split_whitespace())
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replacen("error: ", "", 1)
+}
+
+// ----- config_layers --------------------------------------------------------
+
+/// Returns the config paths to read when none are given to `-c/--config`, in
+/// layers (see `Config.next_layer()`).
+///
+/// If `MGIT_CONFIG` is set, its colon-separated paths make up a single layer.
+/// Otherwise, each of the following that exists is a layer of its own, from the
+/// lowest precedence to the highest (system, user, project):
+///
+/// - `mgit` in each of `$XDG_CONFIG_DIRS` (default `/etc/xdg`), last one first,
+///   since the first is the most important
+/// - `$XDG_CONFIG_HOME/mgit` (default `~/.config/mgit`)
+/// - `~/.mgit`
+/// - `.mgit` in the current directory or the closest parent directory that has
+///   one, stopping at the home directory
+fn config_layers() -> Vec<Vec<String>> {
+    if let Ok(paths) = env::var("MGIT_CONFIG") {
+        if !paths.is_empty() {
+            let paths = paths
+                .split(':')
+                .filter(|path| !path.is_empty())
+                .map(|path| path.to_owned())
+                .collect();
+            return vec![paths];
+        }
+    }
+
+    // The spec says relative paths in the XDG variables should be ignored.
+    let mut candidates = Vec::new();
+    let config_dirs = match env::var("XDG_CONFIG_DIRS") {
+        Ok(ref dirs) if !dirs.is_empty() => dirs.to_owned(),
+        _ => String::from("/etc/xdg"),
+    };
+    for dir in config_dirs.rsplit(':') {
+        if Path::new(dir).is_absolute() {
+            candidates.push(format!("{}/mgit", dir));
+        }
+    }
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(ref dir) if Path::new(dir).is_absolute() => candidates.push(format!("{}/mgit", dir)),
+        _ => candidates.push(String::from("~/.config/mgit")),
+    }
+    candidates.push(String::from("~/.mgit"));
+
+    let home = resolve_path("~", None).ok();
+    if let Ok(cwd) = env::current_dir() {
+        for dir in cwd.ancestors() {
+            if home.iter().any(|home| home.as_path() == dir) {
+                break;
+            }
+            let path = dir.join(".mgit");
+            if path.exists() {
+                if let Some(path) = path.to_str() {
+                    candidates.push(path.to_owned());
+                }
+                break;
+            }
+        }
+    }
+
+    // Only the paths that exist are read, each once.
+    let mut rv: Vec<Vec<String>> = Vec::new();
+    for candidate in candidates {
+        if let Ok(path) = resolve_path(&candidate, None) {
+            if let Some(path) = path.to_str() {
+                if !rv.iter().any(|layer| layer[0] == path) {
+                    rv.push(vec![path.to_owned()]);
+                }
+            }
+        }
+    }
+    rv
+}
+
+// ----- locale_is_utf8 -------------------------------------------------------
+
+/// Returns `true` if the locale (from the first of `LC_ALL`, `LC_CTYPE` and
+/// `LANG` that is set) uses UTF-8. If none are set, the locale is `C`, which does
+/// not.
+fn locale_is_utf8() -> bool {
+    for name in &["LC_ALL", "LC_CTYPE", "LANG"] {
+        if let Some(value) = env::var_os(name) {
+            if !value.is_empty() {
+                let value = value.to_string_lossy().to_lowercase();
+                return value.contains("utf-8") || value.contains("utf8");
+            }
+        }
+    }
+    false
 }
 
 // ----- Command --------------------------------------------------------------
@@ -154,6 +699,10 @@ pub struct Command<'a> {
     /// expects the command to check the invocation and gracefully finish what it's
     /// doing when a sigterm has been received.
     pub exit_on_sigterm: bool,
+    /// Indicates whether mgit warns about repos that are not cloned yet (see
+    /// `Config::uncloned()`) before running the command. Commands that deal with
+    /// those repos themselves set this to `false`.
+    pub warn_uncloned: bool,
     /// Vec of clap arguments for the command.
     pub args: fn() -> Vec<Arg<'a, 'a>>,
     /// Reference to function to invoke when command is called.
@@ -199,112 +748,786 @@ impl Error {
         }
     }
 
-    /// Returns the message describing the error.
-    pub fn message(&self) -> &str {
-        &self.message
+    /// Returns the message describing the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+// ----- resolve_path ---------------------------------------------------------
+
+/// Resolves the given `path` to an absolute path (see `expand_path()`), then
+/// canonicalizes it using `std::fs::canonicalize` and returns it.
+fn resolve_path(path: &str, rel: Option<&str>) -> Result<PathBuf, Error> {
+    match expand_path(path, rel)?.canonicalize() {
+        Ok(path) => Ok(path),
+        Err(e) => Err(Error::new(&format!("failed to canonicalize path ({})", e))),
+    }
+}
+
+// ----- expand_path ----------------------------------------------------------
+
+/// Expands the given `path` to an absolute path, without resolving symlinks or
+/// `..` components.
+///
+/// References to environment variables in the path are expanded first (see
+/// `expand_vars()`).
+///
+/// If the path starts with `~`, this tries to resolve it to a user home directory
+/// (or a subdirectory thereof).
+///
+/// If the path starts with the system `MAIN_SEPARATOR`, it's assumed to be
+/// absolute and is left unchanged.
+///
+/// Otherwise, the path is assumed to be relative to `rel`. If `rel` does not have
+/// a value (i.e. is `None`) then the current working directory is used.
+///
+fn expand_path(path: &str, rel: Option<&str>) -> Result<PathBuf, Error> {
+    let path = expand_vars(path)?;
+    let path = path.as_str();
+    let mut relative_to = match rel {
+        Some(path) => {
+            // Caller passed relative_to. If a directory, return as-is. Otherwise, figure out
+            // the directory containing the path and return that.
+            let buf = PathBuf::from(path);
+            if buf.is_dir() {
+                buf
+            } else {
+                match buf.parent() {
+                    Some(path) => path.to_path_buf(),
+                    None => {
+                        return Err(Error::new(&format!(
+                            "could not get parent of relative_to ({})",
+                            path
+                        )))
+                    }
+                }
+            }
+        }
+        None => match env::current_dir() {
+            Ok(buf) => buf,
+            Err(e) => return Err(Error::new(&format!("could not get cwd ({})", e))),
+        },
+    };
+    Ok(if path.starts_with('~') {
+        // Check for `~` or `~/...` -- i.e. a bare tilde, meaning the current user.
+        if path.len() == 1
+            || path.chars().nth(1).expect("could not get second char") == MAIN_SEPARATOR
+        {
+            let uid = users::get_current_uid();
+            if let Some(user) = users::get_user_by_uid(uid) {
+                let mut buf = user.home_dir().to_path_buf();
+                if path.len() > 2 {
+                    buf.push(&path[2..]);
+                }
+                buf
+            } else {
+                return Err(Error::new(&format!(
+                    "failed to look up user info for uid {}",
+                    uid
+                )));
+            }
+        } else {
+            // Fully specified user (e.g. `~foo/...`) -- extract username and look up home
+            // directory.
+            let name = path[1..].split(MAIN_SEPARATOR).nth(0).unwrap_or_else(|| {
+                panic!(
+                    "splitting '{}' on MAIN_SEPARATOR ('{}') failed",
+                    path, MAIN_SEPARATOR
+                )
+            });
+            if let Some(user) = users::get_user_by_name(name) {
+                let mut buf = user.home_dir().to_path_buf();
+                if path.len() > name.len() + 1 {
+                    buf.push(&path[(name.len() + 2)..]);
+                }
+                buf
+            } else {
+                return Err(Error::new(&format!(
+                    "failed to look up user info for username '{}'",
+                    name
+                )));
+            }
+        }
+    } else if path.starts_with(MAIN_SEPARATOR) {
+        PathBuf::from(path)
+    } else {
+        relative_to.push(path);
+        relative_to
+    })
+}
+
+// ----- symlink_in -----------------------------------------------------------
+
+/// Returns the first path leading up to (and including) `path` that is a symlink,
+/// along with its target, or `None` if there is none.
+fn symlink_in(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let mut ancestors = path.ancestors().collect::<Vec<&Path>>();
+    ancestors.reverse();
+    for ancestor in ancestors {
+        if let Ok(metadata) = fs::symlink_metadata(ancestor) {
+            if metadata.file_type().is_symlink() {
+                let target = fs::read_link(ancestor).unwrap_or_default();
+                return Some((ancestor.to_path_buf(), target));
+            }
+        }
+    }
+    None
+}
+
+// ----- Position -------------------------------------------------------------
+
+/// Position (line and column, both starting at 1) in a config file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// Line number.
+    line: usize,
+    /// Column number, in characters.
+    column: usize,
+}
+
+impl Position {
+    /// Creates and returns a new `Position` instance.
+    fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Returns the line number.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// ----- Positions ------------------------------------------------------------
+
+/// Positions of the section headers and keys in a config file, so that errors can
+/// point at what they are about. Also keeps the values of the keys as written, for
+/// the few that the INI parser would mangle (see `raw_value()`).
+struct Positions {
+    /// Maps section names to the position of their (first) header.
+    sections: HashMap<String, Position>,
+    /// Maps section names (`None` outside of any section) and keys to the position
+    /// of the (first) key.
+    keys: HashMap<(Option<String>, String), Position>,
+    /// Maps section names (`None` outside of any section) and keys to the (last)
+    /// value of the key, as written in the file.
+    values: HashMap<(Option<String>, String), String>,
+}
+
+impl Positions {
+    /// Finds the section headers and keys in `s`, the contents of a config file.
+    fn parse(s: &str) -> Self {
+        let mut sections = HashMap::new();
+        let mut keys = HashMap::new();
+        let mut values = HashMap::new();
+        let mut section = None;
+        for (i, line) in s.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let position = Position::new(i + 1, line.chars().count() - trimmed.chars().count() + 1);
+            let trimmed = trimmed.trim_end();
+            if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                let name = trimmed.trim_start_matches('[').trim_end_matches(']').trim();
+                sections.entry(name.to_owned()).or_insert(position);
+                section = Some(name.to_owned());
+            } else if let Some(j) = trimmed.find(&['=', ':'][..]) {
+                let key = (section.clone(), trimmed[..j].trim().to_owned());
+                values.insert(key.clone(), trimmed[j + 1..].trim().to_owned());
+                keys.entry(key).or_insert(position);
+            }
+        }
+        Self {
+            sections,
+            keys,
+            values,
+        }
+    }
+
+    /// Returns the position of the header of `section`, if found.
+    fn section(&self, section: &str) -> Option<Position> {
+        self.sections.get(section).cloned()
+    }
+
+    /// Returns the position of `key` in `section` (or outside of any section, if
+    /// `section` is `None`). If the key is not found, returns the position of the
+    /// section header instead.
+    fn key(&self, section: Option<&str>, key: &str) -> Option<Position> {
+        match self
+            .keys
+            .get(&(section.map(|s| s.to_owned()), key.to_owned()))
+        {
+            Some(position) => Some(*position),
+            None => section.and_then(|section| self.section(section)),
+        }
+    }
+
+    /// Returns the value of `key` in `section` (or outside of any section, if
+    /// `section` is `None`) as written in the file, if found. Unlike the parsed
+    /// value, quotes and backslashes are left alone, and comments are not removed.
+    fn raw_value(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.values
+            .get(&(section.map(|s| s.to_owned()), key.to_owned()))
+            .map(String::as_str)
+    }
+
+    /// Returns the positions of the keys in `section`.
+    fn keys(&self, section: &str) -> HashMap<String, Position> {
+        let mut rv = HashMap::new();
+        for ((s, key), position) in &self.keys {
+            if let Some(ref s) = *s {
+                if s == section {
+                    rv.insert(key.to_owned(), *position);
+                }
+            }
+        }
+        rv
+    }
+}
+
+// ----- ConfigFile -----------------------------------------------------------
+
+/// Config file, as read by `read_files()`.
+struct ConfigFile {
+    /// Path to the file.
+    path: String,
+    /// Parsed contents of the file.
+    ini: Ini,
+    /// Positions of the section headers and keys in the file.
+    positions: Positions,
+}
+
+// ----- read_files -----------------------------------------------------------
+
+/// Configuration key (outside of any section) for config paths to include.
+const INCLUDE_KEY: &str = "include";
+/// Configuration key (outside of any section) for config paths to include, if
+/// they exist.
+const INCLUDE_IF_EXISTS_KEY: &str = "include_if_exists";
+
+/// Reads and parses the config file at `path`, or if `path` is a directory, the
+/// files in it (recursively) with the extension `.conf`. Returns the files,
+/// along with a list of errors encountered.
+///
+/// Files may include other files (or directories) with the `include` and
+/// `include_if_exists` keys, which hold space-separated lists of paths (see
+/// `split_paths()`), relative to the including file. Included files come before
+/// the including file in the returned list, so that the including file takes
+/// precedence. Each file is only read once: `seen` holds the paths of the files
+/// read so far, and is shared by all the paths of a layer, so that a file included
+/// from several of them (e.g. a team config) is not read again.
+fn read_files(path: &str, seen: &mut Vec<String>) -> (Vec<ConfigFile>, Vec<ConfigError>) {
+    let path_str = path;
+    let path = match resolve_path(path, None) {
+        Ok(buf) => buf,
+        Err(e) => {
+            return (
+                Vec::new(),
+                vec![ConfigError::new(
+                    Category::Unreadable,
+                    path_str,
+                    None,
+                    "failed to resolve config path",
+                    Some(e.message()),
+                )],
+            )
+        }
+    };
+
+    let mut files = Files {
+        chain: Vec::new(),
+        include_position: None,
+        seen: mem::take(seen),
+        rv: Vec::new(),
+        errors: Vec::new(),
+    };
+    files.read_path(&path, path_str);
+    *seen = files.seen;
+    (files.rv, files.errors)
+}
+
+// ----- Files ----------------------------------------------------------------
+
+/// State for `read_files()`.
+struct Files {
+    /// Paths of the files that included the file currently being read, outermost
+    /// first.
+    chain: Vec<String>,
+    /// Position of the include key being followed in the innermost including file.
+    include_position: Option<Position>,
+    /// Paths of the files read so far (in this layer, see `read_files()`).
+    seen: Vec<String>,
+    /// Files read so far.
+    rv: Vec<ConfigFile>,
+    /// Errors encountered so far.
+    errors: Vec<ConfigError>,
+}
+
+impl Files {
+    /// Reads the file at (the resolved) `path`, or if `path` is a directory, the
+    /// files in it with the extension `.conf`. `path_str` is `path` as given by the
+    /// user, for errors.
+    fn read_path(&mut self, path: &Path, path_str: &str) {
+        if path.is_file() {
+            self.read_file(path, path_str);
+        } else if path.is_dir() {
+            for entry in WalkDir::new(path) {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.error(
+                            None,
+                            Category::Unreadable,
+                            path_str,
+                            "failure when walking directory",
+                            &format!("{}", e),
+                        );
+                        continue;
+                    }
+                };
+                if entry.path().is_file() {
+                    if let Some(extension) = entry.path().extension() {
+                        if extension == "conf" {
+                            self.read_file(entry.path(), path_str);
+                        }
+                    }
+                }
+            }
+        } else {
+            self.error(
+                None,
+                Category::Unreadable,
+                path_str,
+                "path is not a file or directory",
+                "",
+            );
+        }
+    }
+
+    /// Reads the file at `path`, and the files it includes. `parent_str` is the path
+    /// (as given by the user) that `path` was found at, for errors.
+    fn read_file(&mut self, path: &Path, parent_str: &str) {
+        let path_str = if let Some(s) = path.to_str() {
+            s
+        } else {
+            self.error(
+                None,
+                Category::Unreadable,
+                parent_str,
+                "subpath contains invalid unicode",
+                "",
+            );
+            return;
+        };
+        if self.chain.iter().any(|path| path == path_str) {
+            let including = self.chain.last().cloned().unwrap_or_default();
+            self.errors.push(
+                ConfigError::new(
+                    Category::Include,
+                    &including,
+                    None,
+                    &format!("include of {} forms a cycle (ignoring include)", path_str),
+                    Some(&format!("include chain: {}", self.chain_to(path_str))),
+                )
+                .at(self.include_position),
+            );
+            return;
+        }
+        if self.seen.iter().any(|path| path == path_str) {
+            return;
+        }
+        self.seen.push(path_str.to_owned());
+
+        let mut f = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                self.error(
+                    None,
+                    Category::Unreadable,
+                    path_str,
+                    "failed to open file",
+                    &format!("{}", e),
+                );
+                return;
+            }
+        };
+        let mut s = String::new();
+        if let Err(e) = f.read_to_string(&mut s) {
+            self.error(
+                None,
+                Category::Unreadable,
+                path_str,
+                "failed to read file",
+                &format!("{}", e),
+            );
+            return;
+        }
+        let ini = match Ini::load_from_str(&s) {
+            Ok(ini) => ini,
+            Err(e) => {
+                // Lines from the parser start at 0, and the column is 0 at the end of
+                // the file.
+                self.error(
+                    Some(Position::new(e.line + 1, e.col.max(1))),
+                    Category::ParseError,
+                    path_str,
+                    "failed to parse file",
+                    &e.msg,
+                );
+                return;
+            }
+        };
+        let positions = Positions::parse(&s);
+
+        if let Some(settings) = ini.section(None::<String>) {
+            // Sorted so that errors are reported in a consistent order.
+            let mut keys = settings.keys().collect::<Vec<&String>>();
+            keys.sort();
+            for key in keys {
+                let required = match key.as_str() {
+                    INCLUDE_KEY => true,
+                    INCLUDE_IF_EXISTS_KEY => false,
+                    _ => {
+                        self.error(
+                            positions.key(None, key),
+                            Category::Setting,
+                            path_str,
+                            &format!("unknown key '{}' outside of a section (ignoring)", key),
+                            "",
+                        );
+                        continue;
+                    }
+                };
+                // The parser would drop a quote at the start of the value (and
+                // backslashes), so the paths are split from the value as written.
+                let value = positions.raw_value(None, key).unwrap_or(&settings[key]);
+                let includes = match split_paths(value) {
+                    Ok(includes) => includes,
+                    Err(e) => {
+                        self.error(
+                            positions.key(None, key),
+                            Category::Include,
+                            path_str,
+                            &format!("failed to parse {} (ignoring)", key),
+                            e.message(),
+                        );
+                        continue;
+                    }
+                };
+                for include in &includes {
+                    let include_path = match resolve_path(include, Some(path_str)) {
+                        Ok(include_path) => include_path,
+                        Err(e) => {
+                            if required {
+                                let cause = format!(
+                                    "{}\ninclude chain: {}",
+                                    e.message(),
+                                    self.chain_to(path_str)
+                                );
+                                self.errors.push(
+                                    ConfigError::new(
+                                        Category::Include,
+                                        path_str,
+                                        None,
+                                        &format!("failed to resolve include {}", include),
+                                        Some(&cause),
+                                    )
+                                    .at(positions.key(None, key)),
+                                );
+                            }
+                            continue;
+                        }
+                    };
+                    self.chain.push(path_str.to_owned());
+                    let include_position =
+                        mem::replace(&mut self.include_position, positions.key(None, key));
+                    self.read_path(&include_path, include);
+                    self.include_position = include_position;
+                    self.chain.pop();
+                }
+            }
+        }
+
+        self.rv.push(ConfigFile {
+            path: path_str.to_owned(),
+            ini,
+            positions,
+        });
+    }
+
+    /// Returns the chain of includes leading to `path`, for errors.
+    fn chain_to(&self, path: &str) -> String {
+        let mut chain = self.chain.clone();
+        chain.push(path.to_owned());
+        chain.join(" \u{2192} ")
+    }
+
+    /// Adds an error in `category` for the config file at `config_path` (at
+    /// `position`, if known), with `cause` (if not
+    /// empty). If the file was included by another, the chain of includes is added
+    /// to the cause.
+    fn error(
+        &mut self,
+        position: Option<Position>,
+        category: Category,
+        config_path: &str,
+        message: &str,
+        cause: &str,
+    ) {
+        let mut cause = cause.to_owned();
+        if !self.chain.is_empty() {
+            if !cause.is_empty() {
+                cause.push('\n');
+            }
+            cause.push_str(&format!("included from {}", self.chain.join(" \u{2192} ")));
+        }
+        self.errors.push(
+            ConfigError::new(
+                category,
+                config_path,
+                None,
+                message,
+                if cause.is_empty() { None } else { Some(&cause) },
+            )
+            .at(position),
+        );
+    }
+}
+
+// ----- expand_vars ----------------------------------------------------------
+
+/// Returns `s` with shell-style references to environment variables replaced by
+/// their values: `$NAME`, `${NAME}`, `${NAME:-default}` (`default` if `NAME` is
+/// unset or empty) and `${NAME-default}` (`default` if `NAME` is unset). Defaults
+/// are expanded too. `$$` is a literal `$`, as is a `$` not followed by a name.
+///
+/// Returns an error if a variable without a default is not set, or a reference is
+/// malformed.
+fn expand_vars(s: &str) -> Result<String, Error> {
+    let is_name_char = |c: char| c == '_' || c.is_ascii_alphanumeric();
+    let mut rv = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        rv.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let next = rest.chars().next();
+        if next == Some('$') {
+            rv.push('$');
+            rest = &rest[1..];
+        } else if next == Some('{') {
+            // Find the matching brace, since defaults may contain references too.
+            let mut depth = 0;
+            let mut end = None;
+            for (i, c) in rest.char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = Some(i);
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let end = match end {
+                Some(end) => end,
+                None => return Err(Error::new(&format!("missing '}}' in '{}'", s))),
+            };
+            let inner = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let name_end = inner.find(|c| !is_name_char(c)).unwrap_or(inner.len());
+            let (name, op) = inner.split_at(name_end);
+            if name.is_empty() {
+                return Err(Error::new(&format!("bad substitution '${{{}}}'", inner)));
+            }
+            let op_len = op
+                .chars()
+                .take(if op.starts_with(':') { 2 } else { 1 })
+                .map(|c| c.len_utf8())
+                .sum();
+            let (op, default) = op.split_at(op_len);
+            match (op, env::var(name)) {
+                ("", _) => rv.push_str(&env_var(name)?),
+                (":-", Ok(ref value)) if !value.is_empty() => rv.push_str(value),
+                ("-", Ok(ref value)) => rv.push_str(value),
+                (":-", _) | ("-", _) => rv.push_str(&expand_vars(default)?),
+                _ => return Err(Error::new(&format!("bad substitution '${{{}}}'", inner))),
+            }
+        } else {
+            let name_end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            if name_end == 0 {
+                rv.push('$');
+            } else {
+                rv.push_str(&env_var(&rest[..name_end])?);
+                rest = &rest[name_end..];
+            }
+        }
+    }
+    rv.push_str(rest);
+    Ok(rv)
+}
+
+// ----- env_var --------------------------------------------------------------
+
+/// Returns the value of the environment variable `name`, or an error if it is not
+/// set (or not valid unicode).
+fn env_var(name: &str) -> Result<String, Error> {
+    match env::var(name) {
+        Ok(value) => Ok(value),
+        Err(env::VarError::NotPresent) => Err(Error::new(&format!(
+            "environment variable {} is not set",
+            name
+        ))),
+        Err(env::VarError::NotUnicode(_)) => Err(Error::new(&format!(
+            "environment variable {} is not valid unicode",
+            name
+        ))),
     }
 }
 
-// ----- resolve_path ---------------------------------------------------------
+// ----- split_paths ----------------------------------------------------------
 
-/// Resolves the given `path`.
-///
-/// If the path starts with `~`, this tries to resolve it to a user home directory
-/// (or a subdirectory thereof).
-///
-/// If the path starts with the system `MAIN_SEPARATOR`, it's assumed to be
-/// absolute and is left unchanged.
-///
-/// Otherwise, the path is assumed to be relative to `rel`. If `rel` does not have
-/// a value (i.e. is `None`) then the current working directory is used.
+/// Splits `s`, a value as written in a config file, into a list of paths,
+/// separated by whitespace. A path containing whitespace (or `;` or `#`) can be
+/// wrapped in double quotes (`"My Configs/team.conf"`), and within quotes, `\"`
+/// and `\\` are a literal `"` and `\`. Outside of quotes, `;` or `#` starts a
+/// comment, which ends the list.
 ///
-/// Once the path has been resolved per the above, it is canonicalized using
-/// `std::fs::canonicalize` and finally returned.
-fn resolve_path(path: &str, rel: Option<&str>) -> Result<PathBuf, Error> {
-    let mut relative_to = match rel {
-        Some(path) => {
-            // Caller passed relative_to. If a directory, return as-is. Otherwise, figure out
-            // the directory containing the path and return that.
-            let buf = PathBuf::from(path);
-            if buf.is_dir() {
-                buf
-            } else {
-                match buf.parent() {
-                    Some(path) => path.to_path_buf(),
-                    None => {
-                        return Err(Error::new(&format!(
-                            "could not get parent of relative_to ({})",
-                            path
-                        )))
-                    }
-                }
+/// Returns an error if a quote is not closed.
+fn split_paths(s: &str) -> Result<Vec<String>, Error> {
+    let mut rv = Vec::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        while let Some(&c) = chars.peek() {
+            if !c.is_whitespace() {
+                break;
             }
+            chars.next();
         }
-        None => match env::current_dir() {
-            Ok(buf) => buf,
-            Err(e) => return Err(Error::new(&format!("could not get cwd ({})", e))),
-        },
-    };
-    let path = if path.starts_with('~') {
-        // Check for `~` or `~/...` -- i.e. a bare tilde, meaning the current user.
-        if path.len() == 1
-            || path.chars().nth(1).expect("could not get second char") == MAIN_SEPARATOR
-        {
-            let uid = users::get_current_uid();
-            if let Some(user) = users::get_user_by_uid(uid) {
-                let mut buf = user.home_dir().to_path_buf();
-                if path.len() > 2 {
-                    buf.push(&path[2..]);
-                }
-                buf
-            } else {
-                return Err(Error::new(&format!(
-                    "failed to look up user info for uid {}",
-                    uid
-                )));
+        match chars.peek() {
+            None | Some(';') | Some('#') => return Ok(rv),
+            _ => (),
+        }
+        let mut path = String::new();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                break;
             }
-        } else {
-            // Fully specified user (e.g. `~foo/...`) -- extract username and look up home
-            // directory.
-            let name = path[1..].split(MAIN_SEPARATOR).nth(0).unwrap_or_else(|| {
-                panic!(
-                    "splitting '{}' on MAIN_SEPARATOR ('{}') failed",
-                    path, MAIN_SEPARATOR
-                )
-            });
-            if let Some(user) = users::get_user_by_name(name) {
-                let mut buf = user.home_dir().to_path_buf();
-                if path.len() > name.len() + 1 {
-                    buf.push(&path[(name.len() + 2)..]);
+            if c == ';' || c == '#' {
+                rv.push(path);
+                return Ok(rv);
+            }
+            if c != '"' {
+                path.push(c);
+                continue;
+            }
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') if chars.peek() == Some(&'"') || chars.peek() == Some(&'\\') => {
+                        path.push(chars.next().expect("expected escaped character"));
+                    }
+                    Some(c) => path.push(c),
+                    None => return Err(Error::new(&format!("unclosed quote in '{}'", s))),
                 }
-                buf
-            } else {
-                return Err(Error::new(&format!(
-                    "failed to look up user info for username '{}'",
-                    name
-                )));
             }
         }
-    } else if path.starts_with(MAIN_SEPARATOR) {
-        PathBuf::from(path)
-    } else {
-        relative_to.push(path);
-        relative_to
-    };
-    match path.canonicalize() {
-        Ok(path) => Ok(path),
-        Err(e) => Err(Error::new(&format!("failed to canonicalize path ({})", e))),
+        rv.push(path);
+    }
+}
+
+// ----- Category -------------------------------------------------------------
+
+/// Categories of warnings, in the order they are listed in the help.
+const CATEGORIES: [Category; 10] = [
+    Category::Unreadable,
+    Category::ParseError,
+    Category::Include,
+    Category::MissingPath,
+    Category::NotARepo,
+    Category::Duplicate,
+    Category::Setting,
+    Category::Theme,
+    Category::Events,
+    Category::Lint,
+];
+
+/// Category of a warning, so that the action taken on it can be chosen per
+/// category (see `WarningPolicy`).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Category {
+    /// A config file or directory could not be found or read.
+    Unreadable,
+    /// A config file is not valid INI.
+    ParseError,
+    /// An include could not be resolved or forms a cycle.
+    Include,
+    /// A repo path could not be resolved (e.g. it does not exist).
+    MissingPath,
+    /// A repo path could not be opened as a git repository.
+    NotARepo,
+    /// A repo is defined more than once in the same layer of configuration.
+    Duplicate,
+    /// A setting (in a `[mgit]` section, a profile, or a repo) is invalid.
+    Setting,
+    /// A theme setting is invalid.
+    Theme,
+    /// Events from `mgit pull --events` could not be written.
+    Events,
+    /// Something in the configuration is likely a mistake (see `Config::lint()`).
+    /// Only reported by `mgit config --check`.
+    Lint,
+}
+
+impl Category {
+    /// Returns the category named `name`, or `None` if there is no such category.
+    fn from_name(name: &str) -> Option<Self> {
+        CATEGORIES
+            .iter()
+            .find(|category| category.name() == name)
+            .cloned()
+    }
+
+    /// Returns the name of the category, as used in `-W <category>=<action>`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Category::Unreadable => "unreadable",
+            Category::ParseError => "parse-error",
+            Category::Include => "include",
+            Category::MissingPath => "missing-path",
+            Category::NotARepo => "not-a-repo",
+            Category::Duplicate => "duplicate",
+            Category::Setting => "setting",
+            Category::Theme => "theme",
+            Category::Events => "events",
+            Category::Lint => "lint",
+        }
     }
 }
 
 // ----- ConfigError ----------------------------------------------------------
 
 /// Represents an error encountered when reading configuration.
+#[derive(Clone)]
 struct ConfigError {
+    /// Category of the error, which determines the action taken on it.
+    category: Category,
     /// Configuration path associated with the error.
     config_path: String,
     /// Path of the repository, if relevant for this error.
     repo_path: Option<String>,
+    /// Position in the config file the error refers to, if known.
+    position: Option<Position>,
     /// Message describing the error.
     message: String,
     /// Optional message indicating the underlying cause of the error.
@@ -313,14 +1536,22 @@ struct ConfigError {
 
 impl ConfigError {
     /// Creates and returns a new `ConfigError` instance.
-    fn new(config_path: &str, repo_path: Option<&str>, message: &str, cause: Option<&str>) -> Self {
+    fn new(
+        category: Category,
+        config_path: &str,
+        repo_path: Option<&str>,
+        message: &str,
+        cause: Option<&str>,
+    ) -> Self {
         Self {
+            category,
             config_path: config_path.to_owned(),
             repo_path: if let Some(path) = repo_path {
                 Some(path.to_owned())
             } else {
                 None
             },
+            position: None,
             message: message.to_owned(),
             cause: if let Some(cause) = cause {
                 Some(cause.to_owned())
@@ -330,6 +1561,18 @@ impl ConfigError {
         }
     }
 
+    /// Sets the position in the config file the error refers to and returns the
+    /// `ConfigError` instance.
+    fn at(mut self, position: Option<Position>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Returns the category of the error.
+    fn category(&self) -> Category {
+        self.category
+    }
+
     /// Returns the underlying cause of the error.
     fn cause(&self) -> Option<&str> {
         if let Some(ref cause) = self.cause {
@@ -344,6 +1587,11 @@ impl ConfigError {
         &self.config_path
     }
 
+    /// Returns the position in the config file the error refers to, if known.
+    fn position(&self) -> Option<Position> {
+        self.position
+    }
+
     /// Returns the message describing the error.
     fn message(&self) -> &str {
         &self.message
@@ -379,6 +1627,21 @@ pub struct Repo {
     symbol: Option<String>,
     /// Optional tags associated with the repo.
     tags: Vec<String>,
+    /// Position of the section header for the repo in the config file, if known.
+    position: Option<Position>,
+    /// Positions of the keys in the repo's section in the config file.
+    key_positions: HashMap<String, Position>,
+    /// Maps the names of the configured remotes to their URLs.
+    remotes: BTreeMap<String, String>,
+    /// Indicates whether the remotes were given with `remote.<name>` keys, rather
+    /// than only by `url` (see `declares_remotes()`).
+    declares_remotes: bool,
+    /// Optional branch to check out when cloning the repo.
+    branch: Option<String>,
+    /// Optional patterns for the names of the remotes to fetch.
+    fetch_remotes: Option<Vec<String>>,
+    /// Optional patterns for the names of the remotes not to fetch.
+    skip_remotes: Option<Vec<String>>,
 }
 
 impl Repo {
@@ -404,7 +1667,125 @@ impl Repo {
                 None => None,
             },
             tags: tags.iter().map(|&s| s.to_owned()).collect(),
+            position: None,
+            key_positions: HashMap::new(),
+            remotes: BTreeMap::new(),
+            declares_remotes: false,
+            branch: None,
+            fetch_remotes: None,
+            skip_remotes: None,
+        }
+    }
+
+    /// Sets the configured `remotes` (mapping names to URLs), whether they were
+    /// `declared` with `remote.<name>` keys, and the `branch` to check out when
+    /// cloning, and returns the `Repo` instance.
+    fn with_remotes(
+        mut self,
+        remotes: BTreeMap<String, String>,
+        declared: bool,
+        branch: Option<&String>,
+    ) -> Self {
+        self.remotes = remotes;
+        self.declares_remotes = declared;
+        self.branch = branch.cloned();
+        self
+    }
+
+    /// Returns the configured remotes as `(name, url)` pairs, sorted by name.
+    pub fn remotes(&self) -> Vec<(&str, &str)> {
+        self.remotes
+            .iter()
+            .map(|(name, url)| (name.as_str(), url.as_str()))
+            .collect()
+    }
+
+    /// Returns `true` if the remotes of the repo were given with `remote.<name>`
+    /// keys. A `url` on its own only says where to clone the repo from, so it does
+    /// not make the configured remotes the full set of remotes the repo should have
+    /// (see `cmd::remotes`).
+    pub fn declares_remotes(&self) -> bool {
+        self.declares_remotes
+    }
+
+    /// Returns the URL to clone the repo from (the URL of the `origin` remote), if
+    /// configured.
+    pub fn url(&self) -> Option<&str> {
+        self.remotes.get(ORIGIN).map(|url| url.as_str())
+    }
+
+    /// Returns the branch to check out when cloning the repo, if configured.
+    #[allow(clippy::match_as_ref)]
+    pub fn branch(&self) -> Option<&str> {
+        match self.branch {
+            Some(ref branch) => Some(branch),
+            None => None,
+        }
+    }
+
+    /// Sets the patterns for the names of the remotes to fetch (`fetch`) and not to
+    /// fetch (`skip`), as whitespace-separated lists, and returns the `Repo`
+    /// instance.
+    fn with_fetch_filter(mut self, fetch: Option<&String>, skip: Option<&String>) -> Self {
+        let patterns = |s: &String| s.split_whitespace().map(|p| p.to_owned()).collect();
+        self.fetch_remotes = fetch.map(patterns);
+        self.skip_remotes = skip.map(patterns);
+        self
+    }
+
+    /// Returns the patterns for the names of the remotes to fetch, if configured.
+    #[allow(clippy::match_as_ref)]
+    pub fn fetch_remotes(&self) -> Option<&[String]> {
+        match self.fetch_remotes {
+            Some(ref patterns) => Some(patterns),
+            None => None,
+        }
+    }
+
+    /// Returns the patterns for the names of the remotes not to fetch, if
+    /// configured.
+    #[allow(clippy::match_as_ref)]
+    pub fn skip_remotes(&self) -> Option<&[String]> {
+        match self.skip_remotes {
+            Some(ref patterns) => Some(patterns),
+            None => None,
+        }
+    }
+
+    /// Returns `true` if the remote named `remote` should be fetched: it matches
+    /// one of the `fetch_remotes` patterns (if configured) and none of the
+    /// `skip_remotes` patterns.
+    pub fn fetches(&self, remote: &str) -> bool {
+        let matches = |patterns: &Vec<String>| patterns.iter().any(|p| glob_match(p, remote));
+        if let Some(ref patterns) = self.fetch_remotes {
+            if !matches(patterns) {
+                return false;
+            }
         }
+        match self.skip_remotes {
+            Some(ref patterns) => !matches(patterns),
+            None => true,
+        }
+    }
+
+    /// Sets the position of the section header for the repo, and of the keys in the
+    /// section, and returns the `Repo` instance.
+    fn at(mut self, position: Option<Position>, key_positions: HashMap<String, Position>) -> Self {
+        self.position = position;
+        self.key_positions = key_positions;
+        self
+    }
+
+    /// Returns the position of the section header for the repo in the configuration
+    /// file in which it was defined, if known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Returns the position of `key` in the repo's section, or of the section header
+    /// if the key is not set there.
+    fn key_position(&self, key: &str) -> Option<Position> {
+        self.key_positions.get(key).cloned().or(self.position)
     }
 
     /// Returns path of configuration file in which this repo was defined.
@@ -472,6 +1853,36 @@ impl Repo {
         }
     }
 
+    /// Takes the settings that are not set for this repo from `other`, an earlier
+    /// definition of the same repo. Tags are only taken if `inherit_tags` is `true`,
+    /// since an empty list of tags is a setting of its own. Remotes are likewise
+    /// taken as a whole (along with whether they were declared) if `inherit_remotes`
+    /// is `true`.
+    fn inherit(&mut self, other: &Repo, inherit_tags: bool, inherit_remotes: bool) {
+        if self.name.is_none() {
+            self.name = other.name.clone();
+        }
+        if self.symbol.is_none() {
+            self.symbol = other.symbol.clone();
+        }
+        if inherit_tags {
+            self.tags = other.tags.clone();
+        }
+        if inherit_remotes {
+            self.remotes = other.remotes.clone();
+            self.declares_remotes = other.declares_remotes;
+        }
+        if self.branch.is_none() {
+            self.branch = other.branch.clone();
+        }
+        if self.fetch_remotes.is_none() {
+            self.fetch_remotes = other.fetch_remotes.clone();
+        }
+        if self.skip_remotes.is_none() {
+            self.skip_remotes = other.skip_remotes.clone();
+        }
+    }
+
     /// Returns a new `git2::Repository` instance for this repo.
     pub fn git(&self) -> Repository {
         Repository::open(&self.full_path)
@@ -503,6 +1914,7 @@ impl Hash for Repo {
 // ----- Field ----------------------------------------------------------------
 
 /// `Repo` field, used to specify what to sort by or iterate through with `Iter`.
+#[derive(Clone, Copy)]
 pub enum Field {
     /// `Repo.path()`
     Path,
@@ -601,52 +2013,447 @@ impl<'a> Iter<'a> {
 impl<'a> Iterator for Iter<'a> {
     type Item = (&'a str, &'a Repo);
 
-    /// Returns the next item in the iterator.
+    /// Returns the next item in the iterator.
+    ///
+    /// If this is the first item, the underlying vector is sorted. The "key" yielded
+    /// depends on the value of `iter_field`. The value is always a reference to a
+    /// `Repo`.
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.sorted {
+            let field = &self.sort_field;
+            self.repos.sort_by_key(|repo| match *field {
+                Field::Name => (repo.name_or_default(), repo.path()),
+                Field::Path => (repo.path(), ""),
+            });
+            self.sorted = true;
+        }
+        if self.repos.is_empty() {
+            None
+        } else {
+            let repo = self.repos.remove(0);
+            let key = match self.iter_field {
+                Field::Name => repo.name_or_default(),
+                Field::Path => repo.path(),
+            };
+            Some((key, repo))
+        }
+    }
+}
+
+// ----- Setting --------------------------------------------------------------
+
+/// Default argument from a `[mgit]` or `[mgit.<command>]` section of the
+/// configuration, or from a profile.
+pub struct Setting {
+    /// Path to the config file that the setting was read from.
+    config_path: String,
+    /// Position of the setting in the config file, if known.
+    position: Option<Position>,
+    /// Name of the profile that the setting is part of, or `None` if it is not part
+    /// of a profile.
+    profile: Option<String>,
+    /// Name of the command from the section name, or `None` for the `[mgit]`
+    /// section.
+    command: Option<String>,
+    /// Key of the setting, which is the long name of the argument.
+    key: String,
+    /// Value of the setting.
+    value: String,
+}
+
+impl Setting {
+    /// Creates and returns a new `Setting` instance.
+    fn new(
+        config_path: &str,
+        position: Option<Position>,
+        profile: Option<&str>,
+        command: Option<&str>,
+        key: &str,
+        value: &str,
+    ) -> Self {
+        Self {
+            config_path: config_path.to_owned(),
+            position,
+            profile: profile.map(|s| s.to_owned()),
+            command: command.map(|s| s.to_owned()),
+            key: key.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    /// Returns the path to the config file that the setting was read from.
+    pub fn config_path(&self) -> &str {
+        &self.config_path
+    }
+
+    /// Returns the position of the setting in the config file, if known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Returns the name of the profile the setting is part of, if any.
+    #[allow(clippy::match_as_ref)]
+    pub fn profile(&self) -> Option<&str> {
+        match self.profile {
+            Some(ref profile) => Some(profile),
+            None => None,
+        }
+    }
+
+    /// Returns the name of the command the setting applies to, or `None` if it
+    /// applies to all commands.
+    #[allow(clippy::match_as_ref)]
+    pub fn command(&self) -> Option<&str> {
+        match self.command {
+            Some(ref command) => Some(command),
+            None => None,
+        }
+    }
+
+    /// Returns the key of the setting.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the value of the setting.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.command {
+            Some(ref command) => write!(f, "{}.{}", command, self.key),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+// ----- Profile --------------------------------------------------------------
+
+/// Prefix for the names of the sections that define profiles.
+const PROFILE_SECTION_PREFIX: &str = "mgit.profile.";
+/// Profile key that specifies the config paths to read.
+const PROFILE_CONFIG_KEY: &str = "config";
+
+/// Named set of config paths and default arguments, as defined in the
+/// `[mgit.profile.<name>]` sections of the configuration and selected with
+/// `-p/--profile`.
+struct Profile {
+    /// Name of the profile.
+    name: String,
+    /// Config paths to read instead of the ones given to `-c/--config`, if any.
+    config_paths: Vec<String>,
+    /// Default arguments.
+    settings: Vec<Setting>,
+}
+
+impl Profile {
+    /// Creates and returns a new, empty `Profile` instance.
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            config_paths: Vec::new(),
+            settings: Vec::new(),
+        }
+    }
+
+    /// Returns the config paths to read instead of the ones given to
+    /// `-c/--config`. If empty, the `-c/--config` paths are read.
+    fn config_paths(&self) -> Vec<String> {
+        self.config_paths.clone()
+    }
+
+    /// Adds the `settings` from a `[mgit.profile.<name>]` section in the config file
+    /// at `config_path` (with the `positions` of its keys), replacing those with the
+    /// same key read from earlier files, and returns a list of errors encountered.
+    ///
+    /// `config` is a space-separated list of config paths, relative to the config
+    /// file. Other keys are either `<key>` or `<command>.<key>`, like the keys in
+    /// the `[mgit]` and `[mgit.<command>]` sections respectively.
+    fn read(
+        &mut self,
+        config_path: &str,
+        settings: &HashMap<String, String>,
+        positions: &Positions,
+    ) -> Vec<ConfigError> {
+        let section = format!("{}{}", PROFILE_SECTION_PREFIX, self.name);
+
+        // Sorted so that errors are reported in a consistent order.
+        let mut keys = settings.keys().collect::<Vec<&String>>();
+        keys.sort();
+
+        let mut rv = Vec::new();
+        for key in keys {
+            let value = &settings[key];
+            let position = positions.key(Some(&section), key);
+            if key == PROFILE_CONFIG_KEY {
+                self.config_paths.clear();
+                for path in value.split_whitespace() {
+                    let full_path = match resolve_path(path, Some(config_path)) {
+                        Ok(full_path) => full_path,
+                        Err(e) => {
+                            rv.push(
+                                ConfigError::new(
+                                    Category::Unreadable,
+                                    config_path,
+                                    None,
+                                    &format!(
+                                        "failed to resolve config path {} in profile {}",
+                                        path, self.name
+                                    ),
+                                    Some(e.message()),
+                                )
+                                .at(position),
+                            );
+                            continue;
+                        }
+                    };
+                    match full_path.to_str() {
+                        Some(s) => self.config_paths.push(s.to_owned()),
+                        None => rv.push(
+                            ConfigError::new(
+                                Category::Unreadable,
+                                config_path,
+                                None,
+                                &format!(
+                                    "config path {} in profile {} is not valid",
+                                    path, self.name
+                                ),
+                                Some("absolute path contains invalid unicode"),
+                            )
+                            .at(position),
+                        ),
+                    }
+                }
+                continue;
+            }
+
+            let (command, key) = match key.find('.') {
+                Some(i) => (Some(&key[..i]), &key[i + 1..]),
+                None => (None, key.as_str()),
+            };
+            self.settings
+                .retain(|setting| setting.command() != command || setting.key() != key);
+            self.settings.push(Setting::new(
+                config_path,
+                position,
+                Some(&self.name),
+                command,
+                key,
+                value,
+            ));
+        }
+        rv
+    }
+}
+
+// ----- read_profile ---------------------------------------------------------
+
+/// Returns the profile named `name` as defined in the (already read and parsed)
+/// config `files`, or `None` if there is no such profile, along with a list of
+/// errors encountered.
+///
+/// A profile may be defined across multiple files, with later definitions
+/// replacing earlier ones key by key.
+fn read_profile(name: &str, files: &[ConfigFile]) -> (Option<Profile>, Vec<ConfigError>) {
+    let section_name = format!("{}{}", PROFILE_SECTION_PREFIX, name);
+    let mut profile = None;
+    let mut errors = Vec::new();
+    for file in files {
+        for (section, settings) in &file.ini {
+            if section.as_ref() == Some(&section_name) {
+                let profile = profile.get_or_insert_with(|| Profile::new(name));
+                errors.append(&mut profile.read(&file.path, settings, &file.positions));
+            }
+        }
+    }
+    (profile, errors)
+}
+
+// ----- Host -----------------------------------------------------------------
+
+/// Facts about the machine mgit is running on, for the conditions in `only_on`
+/// keys (see `Host::matches()`).
+struct Host {
+    /// Hostname of the machine, or `None` if it could not be determined.
+    hostname: Option<String>,
+    /// Name of the operating system, as in `std::env::consts::OS` (e.g. `linux`,
+    /// `macos`).
+    os: &'static str,
+    /// Name of the current user, or `None` if it could not be determined.
+    user: Option<String>,
+}
+
+impl Host {
+    /// Creates and returns a new `Host` instance describing the current machine.
+    fn current() -> Self {
+        let mut buf = [0u8; 256];
+        let hostname = match nix::unistd::gethostname(&mut buf) {
+            Ok(name) => name.to_str().ok().map(|name| name.to_owned()),
+            Err(_) => None,
+        };
+        let user = users::get_current_username().and_then(|name| name.into_string().ok());
+        Self {
+            hostname,
+            os: env::consts::OS,
+            user,
+        }
+    }
+
+    /// Returns `true` if any of the whitespace-separated conditions in `spec`
+    /// holds for this machine.
     ///
-    /// If this is the first item, the underlying vector is sorted. The "key" yielded
-    /// depends on the value of `iter_field`. The value is always a reference to a
-    /// `Repo`.
-    fn next(&mut self) -> Option<Self::Item> {
-        if !self.sorted {
-            let field = &self.sort_field;
-            self.repos.sort_by_key(|repo| match *field {
-                Field::Name => (repo.name_or_default(), repo.path()),
-                Field::Path => (repo.path(), ""),
-            });
-            self.sorted = true;
+    /// Conditions are `hostname=<pattern>`, `os=<pattern>` or `user=<pattern>`; a
+    /// bare `<pattern>` is matched against the hostname. Patterns may contain `*`
+    /// and `?` wildcards (see `glob_match()`). Hostnames are matched both in full
+    /// and without their domain, so `laptop-*` matches `laptop-1.example.com`.
+    fn matches(&self, spec: &str) -> Result<bool, Error> {
+        let mut rv = false;
+        for condition in spec.split_whitespace() {
+            let (kind, pattern) = match condition.find('=') {
+                Some(i) => (&condition[..i], &condition[i + 1..]),
+                None => ("hostname", condition),
+            };
+            rv |= match kind {
+                "hostname" => match self.hostname {
+                    Some(ref hostname) => {
+                        let short = hostname.split('.').next().unwrap_or(hostname);
+                        glob_match(pattern, hostname) || glob_match(pattern, short)
+                    }
+                    None => false,
+                },
+                "os" => glob_match(pattern, self.os),
+                "user" => match self.user {
+                    Some(ref user) => glob_match(pattern, user),
+                    None => false,
+                },
+                _ => {
+                    return Err(Error::new(&format!(
+                        "unknown condition '{}' (expected hostname, os or user)",
+                        kind
+                    )))
+                }
+            };
         }
-        if self.repos.is_empty() {
-            None
+        Ok(rv)
+    }
+}
+
+// ----- glob_match -----------------------------------------------------------
+
+/// Returns `true` if `s` matches `pattern`, where `*` in the pattern matches any
+/// run of characters (including none) and `?` matches any single character.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let s = s.chars().collect::<Vec<char>>();
+    // Position just past the last `*` in the pattern and the position in `s` it is
+    // currently matched up to, for backtracking.
+    let mut star = None;
+    let (mut p, mut i) = (0, 0);
+    while i < s.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            star = Some((p, i));
+        } else if let Some((star_p, star_i)) = star {
+            p = star_p;
+            i = star_i + 1;
+            star = Some((star_p, i));
         } else {
-            let repo = self.repos.remove(0);
-            let key = match self.iter_field {
-                Field::Name => repo.name_or_default(),
-                Field::Path => repo.path(),
-            };
-            Some((key, repo))
+            return false;
         }
     }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 // ----- Config ---------------------------------------------------------------
 
+/// Name of the section reserved for mgit's own settings. Sections named
+/// `mgit.<something>` are reserved as well.
+const MGIT_SECTION: &str = "mgit";
+/// Name of the section for the theme (see the `theme` module).
+const THEME_SECTION: &str = "mgit.theme";
+
 /// Configuration key that specifies repo name.
 const NAME_KEY: &str = "name";
 /// Configuration key that specifies repo symbol.
 const SYMBOL_KEY: &str = "symbol";
 /// Configuration key that specifies repo tags.
 const TAGS_KEY: &str = "tags";
+/// Configuration key that restricts a repo to certain machines (see
+/// `Host::matches()`).
+const ONLY_ON_KEY: &str = "only_on";
+/// Configuration key that specifies the URL to clone the repo from, which is the
+/// URL of the `origin` remote.
+const URL_KEY: &str = "url";
+/// Configuration key that specifies the branch to check out when cloning the repo.
+const BRANCH_KEY: &str = "branch";
+/// Prefix for the configuration keys that specify the URLs of remotes
+/// (`remote.<name>`).
+const REMOTE_KEY_PREFIX: &str = "remote.";
+/// Configuration key that limits the remotes fetched by `pull` to those matching
+/// any of the patterns in its value.
+const FETCH_REMOTES_KEY: &str = "fetch_remotes";
+/// Configuration key that keeps `pull` from fetching the remotes matching any of
+/// the patterns in its value.
+const SKIP_REMOTES_KEY: &str = "skip_remotes";
+/// Name of the remote the repo is cloned from.
+pub const ORIGIN: &str = "origin";
+/// Configuration keys that are valid in repo sections (in addition to the
+/// `remote.<name>` keys).
+const REPO_KEYS: [&str; 8] = [
+    NAME_KEY,
+    SYMBOL_KEY,
+    TAGS_KEY,
+    ONLY_ON_KEY,
+    URL_KEY,
+    BRANCH_KEY,
+    FETCH_REMOTES_KEY,
+    SKIP_REMOTES_KEY,
+];
 
 /// Configuration as specified by the end user.
 pub struct Config {
     /// `Vec` of `Repo` instances defined in the configuration.
     repos: Vec<Repo>,
+    /// Layer of the configuration currently being read (see `next_layer()`).
+    layer: usize,
+    /// Maps the full paths of repos to the layer they were (last) defined in.
+    repo_layers: HashMap<String, usize>,
+    /// Default arguments, as configured in the `[mgit]` and `[mgit.<command>]`
+    /// sections.
+    settings: Vec<Setting>,
+    /// Theme, as configured in the `[mgit.theme]` section(s).
+    theme: Theme,
+    /// Machine mgit is running on, for `only_on` keys.
+    host: Host,
+    /// Lint warnings found while reading the configuration (see `lint()`).
+    lints: Vec<ConfigError>,
+    /// Repos that don't exist yet but can be cloned (see `uncloned()`).
+    uncloned: Vec<Repo>,
+    /// Paths of the config files read so far in the current layer, so that each is
+    /// only read once per layer (see `read_files()`).
+    seen_files: Vec<String>,
 }
 
 impl Config {
     /// Creates and returns a new, empty `Config` instance.
     fn new() -> Self {
-        Self { repos: Vec::new() }
+        Self {
+            repos: Vec::new(),
+            layer: 0,
+            repo_layers: HashMap::new(),
+            settings: Vec::new(),
+            theme: Theme::new(),
+            host: Host::current(),
+            lints: Vec::new(),
+            uncloned: Vec::new(),
+            seen_files: Vec::new(),
+        }
     }
 
     /// Returns an `Iter` instance over the repos in the configuration.
@@ -654,10 +2461,30 @@ impl Config {
         Iter::new(self.repos.iter().collect::<Vec<&Repo>>())
     }
 
+    /// Starts a new layer of configuration. Repos defined in earlier layers may be
+    /// redefined in later ones, with the settings from the later definition taking
+    /// precedence.
+    fn next_layer(&mut self) {
+        self.layer += 1;
+        self.seen_files.clear();
+    }
+
+    /// Returns the default arguments in the configuration.
+    fn settings(&self) -> &[Setting] {
+        &self.settings
+    }
+
+    /// Returns the repos whose paths don't exist (yet) but that have a `url` (or
+    /// `remote.origin`) to clone them from. These are left out of `repos()`, and
+    /// are not reported as missing.
+    fn uncloned(&self) -> &[Repo] {
+        &self.uncloned
+    }
+
     /// Reads configuration at `path`, returning a list of errors encountered.
     ///
     /// If `path` is a directory, it is recursively walked and any files with the
-    /// extension `.conf` are read into the configuration.
+    /// extension `.conf` are read into the configuration (see `read_files()`).
     ///
     /// # Notes
     ///
@@ -671,157 +2498,216 @@ impl Config {
     /// a repository that has already been configured, repository path does not exist
     /// or is not a git repo).
     fn read(&mut self, path: &str) -> Vec<ConfigError> {
-        let path_str = path;
-        let path = match resolve_path(path, None) {
-            Ok(buf) => buf,
-            Err(e) => {
-                return vec![ConfigError::new(
-                    path_str,
-                    None,
-                    "failed to resolve config path",
-                    Some(e.message()),
-                )]
-            }
-        };
-
-        let mut rv = Vec::new();
-        let mut paths = Vec::new();
-        if path.is_file() {
-            paths.push(path);
-        } else if path.is_dir() {
-            for entry in WalkDir::new(&path) {
-                let entry = match entry {
-                    Ok(entry) => entry,
-                    Err(e) => {
-                        rv.push(ConfigError::new(
-                            path_str,
-                            None,
-                            "failure when walking directory",
-                            Some(&format!("{}", e)),
-                        ));
-                        continue;
-                    }
-                };
-                if entry.path().is_file() {
-                    if let Some(extension) = entry.path().extension() {
-                        if extension == "conf" {
-                            paths.push(entry.path().to_path_buf());
-                        }
-                    }
-                }
-            }
-        } else {
-            rv.push(ConfigError::new(
-                path_str,
-                None,
-                "path is not a file or directory",
-                None,
-            ));
-        }
+        let (files, mut rv) = read_files(path, &mut self.seen_files);
 
         let mut full_paths = HashMap::new();
         for repo in &self.repos {
             full_paths.insert(repo.full_path().to_owned(), repo.config_path().to_owned());
         }
 
-        for path in paths {
-            let path_str = if let Some(s) = path.to_str() {
-                s
-            } else {
-                rv.push(ConfigError::new(
-                    path_str,
-                    None,
-                    "subpath contains invalid unicode",
-                    None,
-                ));
-                continue;
-            };
-            let mut f = match File::open(&path) {
-                Ok(f) => f,
-                Err(e) => {
-                    rv.push(ConfigError::new(
-                        path_str,
-                        None,
-                        "failed to open file",
-                        Some(&format!("{}", e)),
-                    ));
-                    continue;
-                }
-            };
-            let mut s = String::new();
-            if let Err(e) = f.read_to_string(&mut s) {
-                rv.push(ConfigError::new(
-                    path_str,
-                    None,
-                    "failed to read file",
-                    Some(&format!("{}", e)),
-                ));
-                continue;
-            }
-            let ini = match Ini::load_from_str(&s) {
-                Ok(ini) => ini,
-                Err(e) => {
-                    rv.push(ConfigError::new(
-                        path_str,
-                        None,
-                        "failed to parse file",
-                        Some(&format!("{}", e)),
-                    ));
-                    continue;
-                }
-            };
-            for (section, settings) in &ini {
+        for file in &files {
+            let path_str = file.path.as_str();
+            let positions = &file.positions;
+            for (section, settings) in &file.ini {
                 let repo_path = if let Some(ref path) = *section {
                     path
                 } else {
                     continue;
                 };
+                let position = positions.section(repo_path);
+                if repo_path == THEME_SECTION {
+                    rv.append(&mut self.read_theme(path_str, settings, positions));
+                    continue;
+                }
+                if repo_path == MGIT_SECTION {
+                    self.read_settings(path_str, None, settings, positions);
+                    continue;
+                }
+                if repo_path.starts_with(PROFILE_SECTION_PREFIX) {
+                    // Profiles are read before the rest of the configuration (see
+                    // `read_profile()`).
+                    continue;
+                }
+                if repo_path.starts_with("mgit.") {
+                    let command = repo_path.trim_start_matches("mgit.");
+                    self.read_settings(path_str, Some(command), settings, positions);
+                    continue;
+                }
+                // Sorted so that lints are reported in a consistent order.
+                let mut keys = settings.keys().collect::<Vec<&String>>();
+                keys.sort();
+                for key in keys {
+                    if !REPO_KEYS.contains(&key.as_str()) && !key.starts_with(REMOTE_KEY_PREFIX) {
+                        self.lints.push(
+                            ConfigError::new(
+                                Category::Lint,
+                                path_str,
+                                Some(repo_path),
+                                &format!("unknown key '{}' (ignoring)", key),
+                                Some(&format!(
+                                    "expected one of: {}, {}<name>",
+                                    REPO_KEYS.join(", "),
+                                    REMOTE_KEY_PREFIX
+                                )),
+                            )
+                            .at(positions.key(Some(repo_path), key)),
+                        );
+                    }
+                }
+                // Repos for other machines are skipped before their paths are looked
+                // at, since they usually don't exist here.
+                if let Some(spec) = settings.get(ONLY_ON_KEY) {
+                    match self.host.matches(spec) {
+                        Ok(true) => (),
+                        Ok(false) => continue,
+                        Err(e) => {
+                            rv.push(
+                                ConfigError::new(
+                                    Category::Setting,
+                                    path_str,
+                                    Some(repo_path),
+                                    "invalid only_on condition (ignoring repo)",
+                                    Some(e.message()),
+                                )
+                                .at(positions.key(Some(repo_path), ONLY_ON_KEY)),
+                            );
+                            continue;
+                        }
+                    }
+                }
+                // `url` is shorthand for `remote.origin`, and wins over it.
+                let mut remotes = BTreeMap::new();
+                let mut declares_remotes = false;
+                for (key, value) in settings {
+                    if key.starts_with(REMOTE_KEY_PREFIX) {
+                        declares_remotes = true;
+                        let name = key.trim_start_matches(REMOTE_KEY_PREFIX);
+                        remotes.insert(name.to_owned(), value.to_owned());
+                    }
+                }
+                if let Some(url) = settings.get(URL_KEY) {
+                    remotes.insert(ORIGIN.to_owned(), url.to_owned());
+                }
                 let full_path = match resolve_path(repo_path, Some(path_str)) {
                     Ok(path) => path,
                     Err(e) => {
-                        rv.push(ConfigError::new(
-                            path_str,
-                            Some(repo_path),
-                            "failed to resolve repo path",
-                            Some(e.message()),
-                        ));
+                        // Repos that can be cloned are expected to be missing until they
+                        // are (see `uncloned()`).
+                        let expanded = expand_path(repo_path, Some(path_str)).ok();
+                        if let (Some(path), true) = (expanded, remotes.contains_key(ORIGIN)) {
+                            if !path.exists() {
+                                if let Some(full_path) = path.to_str() {
+                                    let repo = Repo::new(
+                                        path_str,
+                                        repo_path,
+                                        full_path,
+                                        settings.get(NAME_KEY).map(|s| s.as_str()),
+                                        settings.get(SYMBOL_KEY).map(|s| s.as_str()),
+                                        &settings
+                                            .get(TAGS_KEY)
+                                            .map(|s| s.split_whitespace().collect())
+                                            .unwrap_or_else(Vec::new),
+                                    )
+                                    .at(position, positions.keys(repo_path))
+                                    .with_remotes(
+                                        remotes,
+                                        declares_remotes,
+                                        settings.get(BRANCH_KEY),
+                                    );
+                                    self.uncloned.retain(|other| other.full_path() != full_path);
+                                    self.uncloned.push(repo);
+                                    continue;
+                                }
+                            }
+                        }
+                        rv.push(
+                            ConfigError::new(
+                                Category::MissingPath,
+                                path_str,
+                                Some(repo_path),
+                                "failed to resolve repo path",
+                                Some(e.message()),
+                            )
+                            .at(position),
+                        );
                         continue;
                     }
                 };
                 let full_path_str = if let Some(s) = full_path.to_str() {
                     s
                 } else {
-                    rv.push(ConfigError::new(
-                        path_str,
-                        Some(repo_path),
-                        "absolute path contains invalid unicode",
-                        None,
-                    ));
+                    rv.push(
+                        ConfigError::new(
+                            Category::MissingPath,
+                            path_str,
+                            Some(repo_path),
+                            "absolute path contains invalid unicode",
+                            None,
+                        )
+                        .at(position),
+                    );
                     continue;
                 };
+                // A repo may be redefined in a later layer (but not in the same one), in
+                // which case the new definition takes over the settings it doesn't set
+                // from the old one.
+                let mut previous = None;
                 if let Some(config_path) = full_paths.get(full_path_str) {
-                    rv.push(ConfigError::new(
-                        path_str,
-                        Some(repo_path),
-                        "repo is already configured (ignoring new definition)",
-                        Some(&format!("first configured in {}", config_path)),
-                    ));
-                    continue;
+                    if self.repo_layers.get(full_path_str) == Some(&self.layer) {
+                        rv.push(
+                            ConfigError::new(
+                                Category::Duplicate,
+                                path_str,
+                                Some(repo_path),
+                                "repo is already configured (ignoring new definition)",
+                                Some(&format!("first configured in {}", config_path)),
+                            )
+                            .at(position),
+                        );
+                        continue;
+                    }
+                    previous = self
+                        .repos
+                        .iter()
+                        .position(|repo| repo.full_path() == full_path_str);
                 }
                 if let Err(e) = Repository::open(&full_path) {
-                    rv.push(ConfigError::new(
-                        path_str,
-                        Some(repo_path),
-                        "failed to open repository",
-                        Some(e.message()),
-                    ));
+                    rv.push(
+                        ConfigError::new(
+                            Category::NotARepo,
+                            path_str,
+                            Some(repo_path),
+                            "failed to open repository",
+                            Some(e.message()),
+                        )
+                        .at(position),
+                    );
                     continue;
                 }
+                if let Some((link, target)) = expand_path(repo_path, Some(path_str))
+                    .ok()
+                    .and_then(|path| symlink_in(&path))
+                {
+                    self.lints.push(
+                        ConfigError::new(
+                            Category::Lint,
+                            path_str,
+                            Some(repo_path),
+                            "repo path goes through a symlink",
+                            Some(&format!(
+                                "{} is a symlink to {}",
+                                link.display(),
+                                target.display()
+                            )),
+                        )
+                        .at(position),
+                    );
+                }
                 let tags = match settings.get(TAGS_KEY) {
                     Some(s) => s.split_whitespace().collect::<Vec<&str>>(),
                     None => vec![],
                 };
-                let repo = Repo::new(
+                let mut repo = Repo::new(
                     path_str,
                     repo_path,
                     full_path_str,
@@ -834,12 +2720,175 @@ impl Config {
                         None => None,
                     },
                     tags.as_slice(),
+                )
+                .at(position, positions.keys(repo_path))
+                .with_remotes(remotes, declares_remotes, settings.get(BRANCH_KEY))
+                .with_fetch_filter(
+                    settings.get(FETCH_REMOTES_KEY),
+                    settings.get(SKIP_REMOTES_KEY),
                 );
                 full_paths.insert(full_path_str.to_owned(), path_str.to_owned());
-                self.repos.push(repo);
+                self.repo_layers
+                    .insert(full_path_str.to_owned(), self.layer);
+                match previous {
+                    Some(i) => {
+                        let inherit_remotes = !settings
+                            .keys()
+                            .any(|key| key == URL_KEY || key.starts_with(REMOTE_KEY_PREFIX));
+                        repo.inherit(
+                            &self.repos[i],
+                            !settings.contains_key(TAGS_KEY),
+                            inherit_remotes,
+                        );
+                        self.repos[i] = repo;
+                    }
+                    None => self.repos.push(repo),
+                }
+            }
+        }
+
+        rv
+    }
+
+    /// Returns lint warnings for the configuration: things that are valid, but are
+    /// likely mistakes. These are unknown keys in repo sections, repo paths that go
+    /// through symlinks, display names shared by several repos, and tags that are
+    /// used by only one repo (likely typos).
+    fn lint(&self) -> Vec<ConfigError> {
+        let mut rv = self.lints.clone();
+        let lint = |repo: &Repo, key: &str, message: &str, cause: Option<&str>| {
+            ConfigError::new(
+                Category::Lint,
+                repo.config_path(),
+                Some(repo.path()),
+                message,
+                cause,
+            )
+            .at(repo.key_position(key))
+        };
+
+        let mut names = BTreeMap::new();
+        let mut tags = BTreeMap::new();
+        for repo in &self.repos {
+            names
+                .entry(repo.name_or_default())
+                .or_insert_with(Vec::new)
+                .push(repo);
+            let mut repo_tags = repo.tags();
+            repo_tags.sort();
+            repo_tags.dedup();
+            for tag in repo_tags {
+                tags.entry(tag).or_insert_with(Vec::new).push(repo);
+            }
+        }
+        for (name, repos) in &names {
+            if repos.len() < 2 {
+                continue;
+            }
+            for repo in repos {
+                let others = repos
+                    .iter()
+                    .filter(|other| other.full_path() != repo.full_path())
+                    .map(|other| format!("also used by {}", other.full_path()))
+                    .collect::<Vec<String>>();
+                rv.push(lint(
+                    repo,
+                    NAME_KEY,
+                    &format!("repo name '{}' is not unique", name),
+                    Some(&others.join("\n")),
+                ));
+            }
+        }
+        for (tag, repos) in &tags {
+            if repos.len() == 1 {
+                rv.push(lint(
+                    repos[0],
+                    TAGS_KEY,
+                    &format!("tag '{}' is only used by one repo (typo?)", tag),
+                    None,
+                ));
             }
         }
+        rv
+    }
+
+    /// Adds the settings from `profile`. These take precedence over the settings
+    /// from the `[mgit]` sections (see `defaults()`).
+    fn add_profile(&mut self, profile: Profile) {
+        self.settings.extend(profile.settings);
+    }
+
+    /// Adds the `settings` from a `[mgit]` (if `command` is `None`) or
+    /// `[mgit.<command>]` section in the config file at `config_path` (with the
+    /// `positions` of its keys), replacing those with the same key read from earlier
+    /// files.
+    ///
+    /// The settings are checked later on, when the arguments are known (see
+    /// `defaults()`).
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn read_settings(
+        &mut self,
+        config_path: &str,
+        command: Option<&str>,
+        settings: &HashMap<String, String>,
+        positions: &Positions,
+    ) {
+        let section = match command {
+            Some(command) => format!("{}.{}", MGIT_SECTION, command),
+            None => MGIT_SECTION.to_owned(),
+        };
+
+        // Sorted so that errors are reported in a consistent order.
+        let mut keys = settings.keys().collect::<Vec<&String>>();
+        keys.sort();
+
+        for key in keys {
+            self.settings
+                .retain(|setting| setting.command() != command || setting.key() != key);
+            self.settings.push(Setting::new(
+                config_path,
+                positions.key(Some(&section), key),
+                None,
+                command,
+                key,
+                &settings[key],
+            ));
+        }
+    }
+
+    /// Applies the `settings` from a `[mgit.theme]` section in the config file at
+    /// `config_path` (with the `positions` of its keys) to the theme, returning a
+    /// list of errors encountered.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn read_theme(
+        &mut self,
+        config_path: &str,
+        settings: &HashMap<String, String>,
+        positions: &Positions,
+    ) -> Vec<ConfigError> {
+        // `preset` replaces the base of the theme, so it goes first so that the file's
+        // other keys apply on top of it. The rest are sorted so that errors are
+        // reported in a consistent order.
+        let mut keys = settings.keys().collect::<Vec<&String>>();
+        keys.sort_by_key(|key| (key.as_str() != "preset", key.to_owned()));
 
+        let mut rv = Vec::new();
+        for key in keys {
+            if let Err(e) = self.theme.set(key, &settings[key]) {
+                rv.push(
+                    ConfigError::new(
+                        Category::Theme,
+                        config_path,
+                        None,
+                        &format!("invalid theme setting '{}'", key),
+                        Some(e.message()),
+                    )
+                    .at(positions.key(Some(THEME_SECTION), key)),
+                );
+            }
+        }
         rv
     }
 }
@@ -847,7 +2896,7 @@ impl Config {
 // ----- Action ---------------------------------------------------------------
 
 /// Represents an action to take in response to an error condition.
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum Action {
     /// Ignore the error, do nothing.
     Ignore,
@@ -857,22 +2906,150 @@ enum Action {
     Fatal,
 }
 
+impl Action {
+    /// Returns the action named `name` (`ignore`, `print` or `fatal`), or `None` if
+    /// there is no such action.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ignore" => Some(Action::Ignore),
+            "print" => Some(Action::Print),
+            "fatal" => Some(Action::Fatal),
+            _ => None,
+        }
+    }
+}
+
+// ----- WarningPolicy --------------------------------------------------------
+
+/// Actions to take on warnings, as specified by `-W/--warning`.
+struct WarningPolicy {
+    /// Action for warnings in categories without an action of their own.
+    default: Action,
+    /// Actions for individual categories.
+    actions: HashMap<Category, Action>,
+}
+
+impl WarningPolicy {
+    /// Creates and returns a new `WarningPolicy` instance that prints all warnings.
+    fn new() -> Self {
+        Self {
+            default: Action::Print,
+            actions: HashMap::new(),
+        }
+    }
+
+    /// Applies `value`, which is either an action for all warnings (replacing the
+    /// actions for individual categories set so far) or `<category>=<action>`.
+    fn set(&mut self, value: &str) -> Result<(), Error> {
+        let (category, action) = match value.find('=') {
+            Some(i) => (Some(&value[..i]), &value[i + 1..]),
+            None => (None, value),
+        };
+        let action = Action::from_name(action).ok_or_else(|| {
+            Error::new(&format!(
+                "unknown action '{}' (expected ignore, print or fatal)",
+                action
+            ))
+        })?;
+        match category {
+            Some(name) => match Category::from_name(name) {
+                Some(category) => {
+                    self.actions.insert(category, action);
+                }
+                None => {
+                    return Err(Error::new(&format!(
+                        "unknown category '{}' (expected one of: {})",
+                        name,
+                        CATEGORIES
+                            .iter()
+                            .map(|category| category.name())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    )))
+                }
+            },
+            None => {
+                self.default = action;
+                self.actions.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the action to take on warnings in `category`.
+    fn action(&self, category: Category) -> Action {
+        self.actions.get(&category).cloned().unwrap_or(self.default)
+    }
+}
+
+// ----- ColorWhen ------------------------------------------------------------
+
+/// Represents when to use colors in output, as specified by `--color`.
+enum ColorWhen {
+    /// Use colors if the output is a terminal, unless overridden by the
+    /// environment.
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+impl ColorWhen {
+    /// Returns `true` if colors should be used for output to a stream. `tty`
+    /// indicates whether the stream is a terminal.
+    ///
+    /// In `Auto` mode, a non-empty `NO_COLOR` environment variable turns colors off
+    /// (see https://no-color.org). Otherwise, a `CLICOLOR_FORCE` environment
+    /// variable set to anything other than `0` turns colors on, even if `tty` is
+    /// `false`.
+    fn enabled(&self, tty: bool) -> bool {
+        match *self {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => match (env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE")) {
+                (Some(ref no_color), _) if !no_color.is_empty() => false,
+                (_, Some(ref force)) if !force.is_empty() && force != "0" => true,
+                _ => tty,
+            },
+        }
+    }
+}
+
 // ----- Control --------------------------------------------------------------
 
 /// High level program control – warnings and fatal errors.
 pub struct Control {
     /// Function to call on exit.
     exit: fn(i32),
-    /// Action to take on warnings.
-    warning_action: Action,
+    /// Actions to take on warnings.
+    warning_policy: WarningPolicy,
+    /// Number of warnings registered that were not ignored.
+    warnings: AtomicUsize,
+    /// Indicates whether to use colors in messages.
+    color: bool,
+    /// Indicates whether to use only ASCII characters in messages.
+    ascii: bool,
 }
 
 impl Control {
     /// Creates and returns a new control instance.
-    fn new(exit: fn(i32), warning_action: Action) -> Self {
+    fn new(exit: fn(i32), warning_policy: WarningPolicy, color: bool, ascii: bool) -> Self {
         Self {
             exit,
-            warning_action,
+            warning_policy,
+            warnings: AtomicUsize::new(0),
+            color,
+            ascii,
+        }
+    }
+
+    /// Returns `s` painted in `style`, or `s` as-is if colors are turned off.
+    fn paint(&self, style: Style, s: &str) -> String {
+        if self.color {
+            style.paint(s).to_string()
+        } else {
+            s.to_owned()
         }
     }
 
@@ -890,29 +3067,78 @@ impl Control {
         for _ in 0..label.len() {
             empty.push_str(" ");
         }
+        let message = if self.ascii {
+            to_ascii(message)
+        } else {
+            message.to_owned()
+        };
         for (i, line) in message.lines().enumerate() {
             let margin = if i == 0 { label } else { &empty };
-            eprintln!("{} {}", color.bold().paint(margin), line);
+            eprintln!("{} {}", self.paint(color.bold(), margin), line);
+        }
+    }
+
+    /// Registers a warning for the configuration `error`, in its category.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn config_warning(&self, error: &ConfigError) {
+        let mut s = self.paint(Style::new().bold(), error.message());
+        if let Some(cause) = error.cause() {
+            s.push_str(&format!("\n{}", cause));
+        }
+        let config_path = match error.position() {
+            Some(position) => format!("{}:{}", error.config_path(), position),
+            None => error.config_path().to_owned(),
+        };
+        s.push_str(&format!(
+            "\nin config at path {}",
+            self.paint(Color::Cyan.bold(), &config_path)
+        ));
+        if let Some(repo_path) = error.repo_path() {
+            s.push_str(&format!(
+                "\nfor repo  at path {}",
+                self.paint(Color::Blue.bold(), repo_path)
+            ));
         }
+        self.warning(error.category(), &s);
+    }
+
+    /// Returns the number of warnings registered so far that were not ignored.
+    pub fn warnings(&self) -> usize {
+        self.warnings.load(Ordering::Relaxed)
     }
 
-    /// Registers a warning with the specified `message`.
+    /// Registers a warning in `category` with the specified `message`.
     ///
-    /// The action taken depends on the `warning_action` supplied to the constructor:
+    /// The action taken depends on the action for `category` in the
+    /// `warning_policy` supplied to the constructor:
     ///
     /// * If `Ignore`, nothing is done.
     /// * If `Print`, `message` is printed to stderr.
     /// * If `Fatal`, `message` is printed to stderr, then `fatal()` is called
     /// with an error message noting that warnings are fatal.
-    pub fn warning(&self, message: &str) {
-        if self.warning_action != Action::Ignore {
+    pub fn warning(&self, category: Category, message: &str) {
+        let action = self.warning_policy.action(category);
+        if action != Action::Ignore {
+            self.warnings.fetch_add(1, Ordering::Relaxed);
             self.print("warning", Color::Yellow, message);
-            if self.warning_action == Action::Fatal {
-                self.fatal("encountered warning, warning action is 'fatal'");
+            if action == Action::Fatal {
+                self.fatal(&format!(
+                    "encountered warning, warning action for {} is 'fatal'",
+                    category.name()
+                ));
             }
         }
     }
 
+    /// Exits the process with an exit code of `1`, as mgit does on a termination
+    /// signal for commands that don't handle them (see `Command.exit_on_sigterm`).
+    /// Commands that do handle them call this once they have cleaned up.
+    pub fn interrupted(&self) {
+        eprintln!();
+        (self.exit)(1)
+    }
+
     /// Prints `message` to stderr, then exits the process with an exit code of `1`.
     pub fn fatal(&self, message: &str) {
         self.print("  fatal", Color::Red, message);
@@ -1009,28 +3235,46 @@ pub struct Invocation<'a> {
     config: Config,
     /// `Control` instance.
     control: Control,
+    /// Output format chosen by the user.
+    format: Format,
+    /// Indicates whether to use colors in output to stdout.
+    color: bool,
+    /// Indicates whether to use only ASCII characters in output.
+    ascii: bool,
     /// `Command` instance.
     command: &'a Command<'a>,
     /// `ArgMatches` instance, for the subcommand arguments.
     matches: ArgMatches<'a>,
+    /// `ArgMatches` instance, for the subcommand arguments set in the `[mgit]`
+    /// sections of the configuration.
+    defaults: ArgMatches<'a>,
     /// Number of times the program has receieved a signal to terminate.
     sigterms_received: Arc<AtomicUsize>,
 }
 
 impl<'a> Invocation<'a> {
     /// Creates and returns a new invocation instance.
+    #[allow(clippy::too_many_arguments)]
     fn new(
         sigterm_arc: Arc<AtomicUsize>,
         control: Control,
         config: Config,
+        format: Format,
+        color: bool,
+        ascii: bool,
         command: &'a Command,
         matches: &ArgMatches<'a>,
+        defaults: &ArgMatches<'a>,
     ) -> Self {
         Self {
             config,
             control,
+            format,
+            color,
+            ascii,
             command,
             matches: matches.clone(),
+            defaults: defaults.clone(),
             sigterms_received: sigterm_arc,
         }
     }
@@ -1040,14 +3284,78 @@ impl<'a> Invocation<'a> {
         &self.control
     }
 
+    /// Returns the output format for this invocation.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the default arguments from the configuration.
+    pub fn settings(&self) -> &[Setting] {
+        self.config.settings()
+    }
+
+    /// Returns the repos that don't exist yet but can be cloned (see
+    /// `Config::uncloned()`), limited to those with any of the tags given to the
+    /// `-t/--tag` argument named `arg` (if any), sorted by path.
+    pub fn uncloned(&self, arg: &str) -> Vec<&Repo> {
+        let tags = self.values_of(arg).map(|tags| tags.collect::<Vec<&str>>());
+        let mut rv = self
+            .config
+            .uncloned()
+            .iter()
+            .filter(|repo| match tags {
+                Some(ref tags) => repo.tags().iter().any(|tag| tags.contains(tag)),
+                None => true,
+            })
+            .collect::<Vec<&Repo>>();
+        rv.sort_by_key(|repo| repo.path());
+        rv
+    }
+
+    /// Registers the lint warnings for the configuration (see `Config::lint()`)
+    /// with the control instance.
+    pub fn lint(&self) {
+        for error in self.config.lint() {
+            self.control.config_warning(&error);
+        }
+    }
+
+    /// Returns the theme from the configuration.
+    pub fn theme(&self) -> &Theme {
+        &self.config.theme
+    }
+
+    /// Returns `true` if output to stdout should use colors (see `--color`).
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    /// Returns `true` if output should use only ASCII characters (see `--ascii`).
+    pub fn ascii(&self) -> bool {
+        self.ascii
+    }
+
     /// Returns the command instance for this invocation.
     pub fn command(&self) -> &Command {
         &self.command
     }
 
-    /// Returns the matches struct for this invocation.
-    pub fn matches(&self) -> &ArgMatches {
-        &self.matches
+    /// Returns the value of the argument named `arg`, as supplied by the end user or
+    /// set in the configuration.
+    pub fn value_of(&self, arg: &str) -> Option<&str> {
+        self.matches_for(arg).value_of(arg)
+    }
+
+    /// Returns the values of the argument named `arg`, as supplied by the end user or
+    /// set in the configuration.
+    pub fn values_of(&self, arg: &str) -> Option<Values> {
+        self.matches_for(arg).values_of(arg)
+    }
+
+    /// Returns `true` if the argument named `arg` was supplied by the end user or set
+    /// in the configuration.
+    pub fn is_present(&self, arg: &str) -> bool {
+        self.matches_for(arg).is_present(arg)
     }
 
     /// Increments the number of sigterms received by one.
@@ -1065,10 +3373,26 @@ impl<'a> Invocation<'a> {
     ///
     /// See the documentation for `TagIter` for a full explanation.
     pub fn iter_tags(&self, arg: &str) -> TagIter {
-        let tags = match self.matches().values_of(arg) {
+        let tags = match self.values_of(arg) {
             Some(tags) => Some(tags.collect()),
             None => None,
         };
         TagIter::new(&self.config, tags)
     }
+
+    /// Returns the matches to take the argument named `arg` from: the end user's, if
+    /// they supplied it or its negation (see `negation()`), or if it is not set in
+    /// the configuration either, and the configuration's otherwise.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn matches_for(&self, arg: &str) -> &ArgMatches<'a> {
+        if self.matches.occurrences_of(arg) == 0
+            && !self.matches.is_present(negation_name(arg))
+            && self.defaults.occurrences_of(arg) > 0
+        {
+            &self.defaults
+        } else {
+            &self.matches
+        }
+    }
 }
@@ -0,0 +1,190 @@
+//! `clone` subcommand.
+use std::{collections::HashMap, fs, path::Path};
+
+use clap::Arg;
+use crossbeam_channel::Receiver;
+use git2::Repository;
+
+use app::{Invocation, Repo, ORIGIN};
+use cmd::pull::{run_git, schedule, Events, Results, State, TerminationState};
+use render::Emphasis;
+use ui::{concurrent, Kind, Note, Summary};
+
+/// Name of the command (`clone`).
+pub const NAME: &str = "clone";
+/// One-line description of the command (`clone`).
+pub const ABOUT: &str = "Clones configured repositories that don't exist yet";
+/// This is not a "simple" command. It spawns child processes, and being killed
+/// suddenly could leave a partial clone behind.
+pub const EXIT_ON_SIGTERM: bool = false;
+/// Repos that are not cloned yet are what this command is for, so mgit doesn't
+/// warn about them.
+pub const WARN_UNCLONED: bool = false;
+
+/// Name of the argument for `-c/--concurrent`.
+const CONCURRENT_ARG: &str = "CONCURRENT";
+/// Default number of concurrent clones.
+const CONCURRENT_DEFAULT: &str = "8";
+
+/// Name of the argument for tags.
+const TAG_ARG: &str = "TAG";
+
+/// Group number for errors encountered when cloning.
+const CLONE_FAILURE_GROUP: usize = 0;
+/// Group number for errors encountered when adding remotes.
+const REMOTE_FAILURE_GROUP: usize = 1;
+
+/// Group number for clone successes.
+const CLONE_SUCCESS_GROUP: usize = 100;
+/// Group number for added remotes.
+const REMOTE_SUCCESS_GROUP: usize = 101;
+
+/// Returns the arguments for the command.
+pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name(CONCURRENT_ARG)
+            .default_value(CONCURRENT_DEFAULT)
+            .help("Number of concurrent clones")
+            .short("c")
+            .long("concurrent"),
+        Arg::with_name(TAG_ARG)
+            .help("Limits clone to repos with specified tag(s)")
+            .short("t")
+            .long("tag")
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
+
+/// Executes the `clone` subcommand.
+pub fn run(invocation: &Invocation) {
+    let concurrent = concurrent(invocation, CONCURRENT_ARG);
+    let repos = invocation.uncloned(TAG_ARG);
+    let text = invocation.format().is_text();
+    if repos.is_empty() && text {
+        println!("nothing to clone");
+        return;
+    }
+
+    // Each clone is shown as a fetch of `origin` (the remote it is cloned from).
+    let mut events = Events::new(None);
+    let mut results: Results = HashMap::new();
+    let mut remotes = Vec::new();
+    for repo in &repos {
+        results.insert(repo, Summary::new());
+        remotes.push((*repo, ORIGIN.to_owned(), State::Pending));
+    }
+    if !remotes.is_empty() {
+        let termination_state = schedule(
+            invocation,
+            concurrent,
+            text,
+            remotes,
+            &mut results,
+            &mut events,
+            clone,
+        );
+        if termination_state == TerminationState::Hard {
+            if text {
+                println!();
+            }
+            return;
+        }
+    }
+
+    let mut renderer = invocation.format().renderer(invocation, Emphasis::RESULTS);
+    renderer.tag(None);
+    for repo in repos {
+        let summary = results
+            .get(repo)
+            .expect("failed to look up results for repo");
+        renderer.repo(repo, repo.name_or_default(), summary);
+    }
+    print!("{}", renderer.finish());
+}
+
+// ----- clone ----------------------------------------------------------------
+
+/// Clones `repo` from its URL (see `Repo::url()`) using the git executable (see
+/// `pull::fetch_and_ff()` for why), checking out its branch, if configured. Then
+/// adds the other configured remotes to the clone.
+///
+/// Parent directories are created as needed. If the clone is killed by the user,
+/// the partial clone is removed.
+fn clone(term_rx: &Receiver<bool>, repo: &Repo, _: &str) -> Summary {
+    let mut summary = Summary::new();
+    let url = repo.url().expect("expected repo to have a url");
+    let path = Path::new(repo.full_path());
+    let parent = path.parent().expect("expected repo path to have a parent");
+    if let Err(e) = fs::create_dir_all(parent) {
+        summary.push_note(Note::new(
+            CLONE_FAILURE_GROUP,
+            Kind::Failure,
+            &format!("failed to create directory {} ({})", parent.display(), e),
+        ));
+        return summary;
+    }
+
+    let mut args = vec!["clone"];
+    if let Some(branch) = repo.branch() {
+        args.push("--branch");
+        args.push(branch);
+    }
+    // Keep a url like `--upload-pack=...` from being taken for an option.
+    args.push("--");
+    args.push(url);
+    args.push(repo.full_path());
+    match run_git(term_rx, &parent.to_string_lossy(), &args) {
+        Some(Ok(())) => {
+            summary.push_note(Note::new(
+                CLONE_SUCCESS_GROUP,
+                Kind::Success,
+                &format!("cloned from {}", url),
+            ));
+        }
+        Some(Err(message)) => {
+            summary.push_note(Note::new(
+                CLONE_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to clone from {}: {}", url, message),
+            ));
+            return summary;
+        }
+        None => {
+            // The repo did not exist before (see `Config::uncloned()`), so anything
+            // at its path is left over from the clone.
+            let _ = fs::remove_dir_all(path);
+            return summary;
+        }
+    }
+
+    let git = match Repository::open(path) {
+        Ok(git) => git,
+        Err(e) => {
+            summary.push_note(Note::new(
+                REMOTE_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to open clone ({})", e),
+            ));
+            return summary;
+        }
+    };
+    for (name, url) in repo.remotes() {
+        if name == ORIGIN {
+            continue;
+        }
+        match git.remote(name, url) {
+            Ok(_) => summary.push_note(Note::new(
+                REMOTE_SUCCESS_GROUP,
+                Kind::Success,
+                &format!("added remote {} ({})", name, url),
+            )),
+            Err(e) => summary.push_note(Note::new(
+                REMOTE_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to add remote {} ({})", name, e.message()),
+            )),
+        }
+    }
+    summary
+}
@@ -1,9 +1,9 @@
 //! `config` subcommand.
-use ansi_term::{Color, Style};
 use clap::Arg;
-use indexmap::IndexMap;
 
-use app::{Field, Invocation};
+use app::{negation, Field, Invocation, Position, Setting};
+use render::{each_repo, Emphasis};
+use ui::{Kind, Note, Summary};
 
 /// Name of the command (`config`).
 pub const NAME: &str = "config";
@@ -11,15 +11,27 @@ pub const NAME: &str = "config";
 pub const ABOUT: &str = "Prints configuration as interpreted by mgit";
 /// This is a "simple" command.
 pub const EXIT_ON_SIGTERM: bool = true;
+/// mgit warns about repos that are not cloned yet before running this command.
+pub const WARN_UNCLONED: bool = true;
 
+/// Name of the argument for `--check`.
+const CHECK_ARG: &str = "CHECK";
+/// Name of the argument for `--no-check`.
+const NO_CHECK_ARG: &str = "NO_CHECK";
 /// Name of the argument for `-t/--tag`.
 const TAG_ARG: &str = "TAG";
 /// Name of the argument for `-v/--verbose`.
 const VERBOSE_ARG: &str = "VERBOSE";
+/// Name of the argument for `--no-verbose`.
+const NO_VERBOSE_ARG: &str = "NO_VERBOSE";
 
 /// Returns the arguments for the command.
 pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
     vec![
+        Arg::with_name(CHECK_ARG)
+            .help("Checks the configuration for problems and exits non-zero if there are any")
+            .long("check"),
+        negation(NO_CHECK_ARG, "no-check", CHECK_ARG),
         Arg::with_name(TAG_ARG)
             .help("Limits/groups display to repos with specified tag(s)")
             .short("t")
@@ -27,100 +39,135 @@ pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
             .multiple(true)
             .number_of_values(1),
         Arg::with_name(VERBOSE_ARG)
-            .help("Shows defaults in addition to user-specified config")
+            .help("Shows defaults and [mgit] settings in addition to user-specified config")
             .short("v")
             .long("verbose"),
+        negation(NO_VERBOSE_ARG, "no-verbose", VERBOSE_ARG),
     ]
 }
 
 /// Executes the `config` subcommand.
 pub fn run(invocation: &Invocation) {
-    let verbose = invocation.matches().is_present(VERBOSE_ARG);
-    let header = Style::new().bold().underline();
-    for (tag, repos) in invocation.iter_tags(TAG_ARG) {
-        if let Some(tag) = tag {
-            println!("\n{}{}", header.paint("TAG:"), header.paint(tag));
-        } else {
-            println!();
+    if invocation.is_present(CHECK_ARG) {
+        return check(invocation);
+    }
+
+    let verbose = invocation.is_present(VERBOSE_ARG);
+    let mut renderer = invocation.format().renderer(invocation, Emphasis::RESULTS);
+
+    // Settings from the [mgit] sections and the profile come first, as keys of the
+    // form `<key>` or `<command>.<key>` along with where they came from.
+    let mut settings = invocation.settings().iter().collect::<Vec<&Setting>>();
+    settings.sort_by_key(|setting| (setting.profile(), setting.command(), setting.key()));
+    if verbose && !settings.is_empty() {
+        let mut summary = Summary::new();
+        for (group, setting) in settings.into_iter().enumerate() {
+            let config_path = with_line(setting.config_path(), setting.position());
+            summary.push_note(
+                Note::new(
+                    group,
+                    Kind::None,
+                    &match setting.profile() {
+                        Some(profile) => format!(
+                            "{} (profile {} in {})",
+                            setting.value(),
+                            profile,
+                            config_path
+                        ),
+                        None => format!("{} (in {})", setting.value(), config_path),
+                    },
+                )
+                .with_key(&format!("{}", setting)),
+            );
         }
-        for (path, repo) in repos.iter_field(Field::Path).sorted_by(Field::Path) {
-            // Compute and take references to certain values. We do this before creating the
-            // `info` map below so that things are deallocated in the correct order.
-            let name_default = &format!("{} (default)", repo.name_or_default());
-            let symbol_default = &format!("{} (default)", repo.symbol_or_default());
+        renderer.section("mgit settings", &summary);
+    }
 
+    each_repo(
+        invocation,
+        TAG_ARG,
+        Field::Path,
+        &mut *renderer,
+        |renderer, repo, name| {
             let tags_vec = repo.tags();
             let tags = if tags_vec.is_empty() {
                 String::from("<none set>")
             } else {
-                let mut s = String::new();
-                for (i, tag) in tags_vec.iter().enumerate() {
-                    if i != 0 {
-                        s.push_str(", ");
-                    }
-                    s.push_str(tag);
-                }
-                s
+                tags_vec.join(", ")
             };
 
-            // Buffer information into a hashmap that iterates in insertion order. We need to
-            // buffer since we want to draw ┖ on the last line instead of ┠, and we don't
-            // know what the last line is until we look at all the settings (taking `verbose`
-            // into consideration).
-            let mut info = IndexMap::new();
-            info.insert("config", repo.config_path());
-            info.insert("path", repo.full_path());
+            // Each setting becomes a keyed note, in the order they should be displayed.
+            // Settings with no value are only included (with their default values) if
+            // `verbose` is set.
+            let mut info = Vec::new();
+            info.push(("config", with_line(repo.config_path(), repo.position())));
+            info.push(("path", repo.full_path().to_owned()));
             match repo.name() {
-                Some(name) => {
-                    info.insert("name", name);
-                }
+                Some(name) => info.push(("name", name.to_owned())),
                 None => {
                     if verbose {
-                        info.insert("name", name_default);
+                        info.push(("name", format!("{} (default)", repo.name_or_default())));
                     }
                 }
             }
             match repo.symbol() {
-                Some(symbol) => {
-                    info.insert("symbol", symbol);
-                }
+                Some(symbol) => info.push(("symbol", symbol.to_owned())),
                 None => {
                     if verbose {
-                        info.insert("symbol", symbol_default);
+                        info.push(("symbol", format!("{} (default)", repo.symbol_or_default())));
                     }
                 }
             }
             if verbose || !tags_vec.is_empty() {
-                info.insert("tags", &tags);
+                info.push(("tags", tags));
+            }
+            if let Some(branch) = repo.branch() {
+                info.push(("branch", branch.to_owned()));
+            }
+            if let Some(patterns) = repo.fetch_remotes() {
+                info.push(("fetch_remotes", patterns.join(" ")));
+            }
+            if let Some(patterns) = repo.skip_remotes() {
+                info.push(("skip_remotes", patterns.join(" ")));
+            }
+            let remotes = repo
+                .remotes()
+                .into_iter()
+                .map(|(name, url)| (format!("remote.{}", name), url.to_owned()))
+                .collect::<Vec<(String, String)>>();
+            for (key, url) in &remotes {
+                info.push((key, url.clone()));
             }
 
-            // Pretty-print information, "keyed" by the user-specified path from the
-            // configuration.
-            println!("{}", Color::Purple.bold().paint(path));
-            for (i, (key, value)) in info.iter().enumerate() {
-                // 2500 is "─" (light horizontal box drawing character)
-                let mut h = String::from("\u{2500}");
-                // Left-pad with light horizontal bar
-                for _ in 0..6 - key.len() {
-                    h.push_str("\u{2500}");
-                }
-                h.push_str(" ");
-                // 2516 is "┖" (up heavy and right light)
-                // 2510 is "┠" (vertical heavy and right light)
-                // Use 2516 for the last item, 2510 for all others.
-                let v = if i == (info.len() - 1) {
-                    "\u{2516}"
-                } else {
-                    "\u{2520}"
-                };
-                // Box-drawing chars and key in blue, value in default terminal color.
-                println!(
-                    "{}{}",
-                    Color::Blue.paint(format!("  {}{}{}: ", v, h, key)),
-                    value
-                )
+            let mut summary = Summary::new();
+            for (group, (key, value)) in info.into_iter().enumerate() {
+                summary.push_note(Note::new(group, Kind::None, &value).with_key(key));
             }
-        }
+            renderer.settings(repo, name, &summary);
+        },
+    );
+    print!("{}", renderer.finish());
+}
+
+/// Returns `config_path`, followed by the line of `position` if known.
+fn with_line(config_path: &str, position: Option<Position>) -> String {
+    match position {
+        Some(position) => format!("{}:{}", config_path, position.line()),
+        None => config_path.to_owned(),
+    }
+}
+
+/// Runs the lint rules on top of the checks done when reading the configuration,
+/// then exits with an error if any warnings (that were not ignored) came up.
+fn check(invocation: &Invocation) {
+    invocation.lint();
+    match invocation.control().warnings() {
+        0 => println!("no problems found"),
+        1 => invocation
+            .control()
+            .fatal("configuration check found 1 problem"),
+        n => invocation
+            .control()
+            .fatal(&format!("configuration check found {} problems", n)),
     }
-    println!();
 }
@@ -1,4 +1,6 @@
 //! Subcommands.
+pub mod clone;
 pub mod config;
 pub mod pull;
+pub mod remotes;
 pub mod status;
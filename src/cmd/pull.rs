@@ -1,18 +1,19 @@
 //! `pull` subcommand.
 use std::{
     collections::{HashMap, HashSet},
+    fs::OpenOptions,
     io::{stdout, Write},
     os::unix::process::CommandExt,
     process::{Command, Stdio},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, SystemTime},
 };
 
 use ansi_term::{Color, Style};
 use clap::Arg;
 use crossbeam;
 use crossbeam_channel::{self, Receiver, Sender};
-use git2::{ObjectType, ResetType, StatusOptions, StatusShow};
+use git2::{ObjectType, Repository, ResetType, StatusOptions, StatusShow};
 use libc;
 use nix;
 use termion::{
@@ -22,8 +23,14 @@ use termion::{
     raw::{IntoRawMode, RawTerminal},
 };
 
-use app::{Invocation, Repo};
-use ui::{Kind, Note, Summary, TrackingBranches};
+use app::{glob_match, Category, Field, Invocation, Repo};
+use render::{paint, render, truncate, Emphasis};
+use theme::Theme;
+use ui::{
+    concurrent, ellipsis, format_duration, format_timestamp, json_string, last_fetched,
+    parse_duration, to_ascii, to_ascii_glyph, Kind, Note, Resize, ResizeDebounce, Summary,
+    TrackingBranches,
+};
 
 /// Name of the command (`pull`).
 pub const NAME: &str = "pull";
@@ -33,12 +40,25 @@ pub const ABOUT: &str = "Fetches from remotes and fast-forwards local tracking b
 /// amenable to suddenly being killed (could cause an incomplete write to the git
 /// repo).
 pub const EXIT_ON_SIGTERM: bool = false;
+/// mgit warns about repos that are not cloned yet before running this command.
+pub const WARN_UNCLONED: bool = true;
 
 /// Name of the argument for `-c/--concurrent`.
 const CONCURRENT_ARG: &str = "CONCURRENT";
 /// Default number of concurrent fetches.
 const CONCURRENT_DEFAULT: &str = "8";
 
+/// Name of the argument for `--events`.
+const EVENTS_ARG: &str = "EVENTS";
+/// Name of the argument for `--events-file`.
+const EVENTS_FILE_ARG: &str = "EVENTS_FILE";
+
+/// Name of the argument for `--older-than`.
+const OLDER_THAN_ARG: &str = "OLDER_THAN";
+
+/// Name of the argument for `-r/--remote`.
+const REMOTE_ARG: &str = "REMOTE";
+
 /// Name of the argument for tags.
 const TAG_ARG: &str = "TAG";
 
@@ -47,6 +67,8 @@ const FETCH_FAILURE_GROUP: usize = 0;
 /// Group number for errors encountered when fetching.
 const BRANCH_FAILURE_GROUP: usize = 1;
 
+/// Group number for remotes skipped because they were fetched recently.
+const FETCH_SKIPPED_GROUP: usize = 99;
 /// Group number for fetch successes.
 const FETCH_SUCCESS_GROUP: usize = 100;
 /// Group number for branch status messages.
@@ -56,11 +78,8 @@ const BRANCH_STATUS_GROUP: usize = 101;
 /// showing the status.
 const UPDATE_FREQUENCY: u64 = 100;
 
-/// Number of milliseconds after which a terminal resize is considered "settled."
-const DEBOUNCE_MILLIS: u64 = 500;
-
 /// Convenience type for a `HashMap` mapping a `Repo` to its `Summary`.
-type Results<'a> = HashMap<&'a Repo, Summary>;
+pub type Results<'a> = HashMap<&'a Repo, Summary>;
 
 /// Returns the arguments for the command.
 pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
@@ -70,6 +89,28 @@ pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
             .help("Number of concurrent fetches")
             .short("c")
             .long("concurrent"),
+        Arg::with_name(EVENTS_ARG)
+            .help("Writes an event for each change in the state of a remote")
+            .long("events")
+            .possible_values(&["ndjson"])
+            .takes_value(true)
+            .value_name("FORMAT"),
+        Arg::with_name(EVENTS_FILE_ARG)
+            .help("Appends events to a file instead of writing them to stdout")
+            .long("events-file")
+            .requires(EVENTS_ARG)
+            .value_name("PATH"),
+        Arg::with_name(OLDER_THAN_ARG)
+            .help("Only fetches remotes not fetched within DURATION (e.g. 30m, 2w)")
+            .long("older-than")
+            .value_name("DURATION"),
+        Arg::with_name(REMOTE_ARG)
+            .help("Limits fetches to remotes matching PATTERN (may use * and ?)")
+            .short("r")
+            .long("remote")
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("PATTERN"),
         Arg::with_name(TAG_ARG)
             .help("Limits pull to repos with specified tag(s)")
             .short("t")
@@ -81,25 +122,51 @@ pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
 
 /// Executes the `pull` subcommand.
 pub fn run(invocation: &Invocation) {
-    let concurrent_str = invocation
-        .matches()
-        .value_of(CONCURRENT_ARG)
-        .unwrap_or_else(|| panic!("expected {} to have an argument", CONCURRENT_ARG));
-    let concurrent = match concurrent_str.parse::<u8>() {
-        Ok(concurrent) => concurrent,
-        Err(e) => {
-            return invocation.control().fatal(&format!(
-                "failed to interpret value '{}' for {} ({})",
-                concurrent_str, CONCURRENT_ARG, e
-            ));
+    let concurrent = concurrent(invocation, CONCURRENT_ARG);
+    let older_than = match invocation.value_of(OLDER_THAN_ARG) {
+        Some(older_than_str) => match parse_duration(older_than_str) {
+            Ok(older_than) => Some(older_than),
+            Err(e) => {
+                return invocation.control().fatal(&format!(
+                    "failed to interpret value '{}' for {} ({})",
+                    older_than_str,
+                    OLDER_THAN_ARG,
+                    e.message()
+                ));
+            }
+        },
+        None => None,
+    };
+
+    // Events go to the file from --events-file, if supplied, otherwise to stdout. In
+    // the latter case there's no room for the terminal UI (or the summary at the
+    // end), since stdout is reserved for the event stream. Formats other than text
+    // get no terminal UI either, so that stdout holds just the rendered document.
+    let mut show_ui = invocation.format().is_text();
+    let mut events_on_stdout = false;
+    let mut events = if invocation.is_present(EVENTS_ARG) {
+        match invocation.value_of(EVENTS_FILE_ARG) {
+            Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Events::new(Some(Box::new(file))),
+                Err(e) => {
+                    return invocation
+                        .control()
+                        .fatal(&format!("failed to open events file {} ({})", path, e));
+                }
+            },
+            None => {
+                show_ui = false;
+                events_on_stdout = true;
+                Events::new(Some(Box::new(stdout())))
+            }
         }
+    } else {
+        Events::new(None)
     };
-    if concurrent < 1 {
-        invocation.control().fatal(&format!(
-            "{} must be one or greater (got '{}')",
-            CONCURRENT_ARG, concurrent
-        ));
-    }
+
+    let remote_patterns = invocation
+        .values_of(REMOTE_ARG)
+        .map(|patterns| patterns.collect::<Vec<&str>>());
 
     // Make a list of the repos we need to fetch, taking -t/--tag into account.
     let mut repo_set = HashSet::new();
@@ -109,10 +176,8 @@ pub fn run(invocation: &Invocation) {
         }
     }
 
-    // `remotes` starts as a vec of all the `(&Repo, remote: &str)` pairs we need to
-    // fetch. As fetch threads become available, items are popped from the front of
-    // this vec. Once the vec is empty, we're done. (...after we wait for the current
-    // fetches to finish, of course.)
+    // `remotes` is a vec of all the `(&Repo, remote, state)` triples, where the
+    // remotes we need to fetch are `Pending` (see `schedule()`).
     let mut remotes = Vec::new();
 
     // `results` maps a `&Repo` to its `Summary`. Fetch threads trasmit `Summary`
@@ -120,58 +185,226 @@ pub fn run(invocation: &Invocation) {
     // `Summary` stored in this map.
     let mut results: Results = HashMap::new();
 
+    // Initialize `remotes` and `results`. Remotes that are not selected by -r/--remote
+    // or the repo's configuration (see `Repo::fetches()`) are skipped rather than
+    // queued, as are remotes that were fetched recently enough with --older-than.
+    for repo in repo_set {
+        let mut summary = Summary::new();
+        let git = repo.git();
+        let mut not_selected = Vec::new();
+        let mut excluded = Vec::new();
+        match git.remotes() {
+            Ok(names) => {
+                for name in names.iter() {
+                    if let Some(name) = name {
+                        let selected = match remote_patterns {
+                            Some(ref patterns) => {
+                                patterns.iter().any(|pattern| glob_match(pattern, name))
+                            }
+                            None => true,
+                        };
+                        if !selected || !repo.fetches(name) {
+                            if selected {
+                                excluded.push(name.to_owned());
+                            } else {
+                                not_selected.push(name.to_owned());
+                            }
+                            events.emit(repo, name, &State::Skipped, None);
+                            remotes.push((repo, name.to_owned(), State::Skipped));
+                            continue;
+                        }
+                        let recent = older_than
+                            .and_then(|older_than| fetched_within(&git, name, older_than));
+                        match recent {
+                            Some(age) => {
+                                summary.push_note(Note::new(
+                                    FETCH_SKIPPED_GROUP,
+                                    Kind::None,
+                                    &format!(
+                                        "skipped {} (last fetched {} ago)",
+                                        name,
+                                        format_duration(age)
+                                    ),
+                                ));
+                                events.emit(repo, name, &State::Skipped, None);
+                                remotes.push((repo, name.to_owned(), State::Skipped));
+                            }
+                            None => {
+                                events.emit(repo, name, &State::Pending, None);
+                                remotes.push((repo, name.to_owned(), State::Pending));
+                            }
+                        }
+                    } else {
+                        summary.push_note(Note::new(
+                            FETCH_FAILURE_GROUP,
+                            Kind::Failure,
+                            "skipped remote with invalid utf-8 name",
+                        ));
+                    }
+                }
+            }
+            Err(e) => {
+                summary.push_note(Note::new(
+                    FETCH_FAILURE_GROUP,
+                    Kind::Failure,
+                    &format!("failed to get remotes ({})", e),
+                ));
+            }
+        }
+        if !not_selected.is_empty() {
+            summary.push_note(Note::new(
+                FETCH_SKIPPED_GROUP,
+                Kind::None,
+                &format!(
+                    "skipped {} (not selected by --remote)",
+                    not_selected.join(", ")
+                ),
+            ));
+        }
+        if !excluded.is_empty() {
+            summary.push_note(Note::new(
+                FETCH_SKIPPED_GROUP,
+                Kind::None,
+                &format!(
+                    "skipped {} (excluded by configuration)",
+                    excluded.join(", ")
+                ),
+            ));
+        }
+        // Failures so far are about the repo rather than a remote, so they would not
+        // show up in any remote's event.
+        if summary.kind() == Kind::Failure {
+            events.emit_failure(repo, &summary);
+        }
+        results.insert(repo, summary);
+    }
+
+    let termination_state = schedule(
+        invocation,
+        concurrent,
+        show_ui,
+        remotes,
+        &mut results,
+        &mut events,
+        fetch_and_ff,
+    );
+
+    if let Some(error) = events.error() {
+        invocation.control().warning(
+            Category::Events,
+            &format!("stopped writing events ({})", error),
+        );
+    }
+
+    // The summary is already in the event stream.
+    if events_on_stdout {
+        return;
+    }
+
+    // If the user sent two sigterms, assume it signals the intent "get me the hell
+    // out of here as quickly as possible" -- don't bother them with a summary.
+    if termination_state == TerminationState::Hard {
+        if show_ui {
+            println!();
+        }
+        return;
+    }
+
+    render(
+        invocation,
+        TAG_ARG,
+        Field::Name,
+        Emphasis::RESULTS,
+        |repo| {
+            results
+                .get(repo)
+                .expect("failed to look up results for repo")
+                .clone()
+        },
+    );
+}
+
+// ----- schedule -------------------------------------------------------------
+
+/// Runs `job` for each of the `(repo, remote, state)` triples in `remotes` whose
+/// state is `Pending`, with up to `concurrent` jobs at a time, and returns the
+/// state of termination at the end. The others are only shown.
+///
+/// `job` is called (on its own thread) with a channel on which it receives a
+/// message if it should terminate immediately, the repo and the name of the
+/// remote, and returns a `Summary`, which is merged into the repo's summary in
+/// `results`. The state of the remote follows from the kind of the summary.
+///
+/// If `show_ui` is `true`, progress is shown in the terminal (see `UI`). Changes in
+/// state are written to `events`.
+pub fn schedule<'a, F>(
+    invocation: &'a Invocation,
+    concurrent: u8,
+    show_ui: bool,
+    remotes: Vec<(&'a Repo, String, State)>,
+    results: &mut Results<'a>,
+    events: &mut Events,
+    job: F,
+) -> TerminationState
+where
+    F: Fn(&Receiver<bool>, &'a Repo, &str) -> Summary + Sync,
+{
     // Iterator on which we check `next()` for Ctrl-c from the user. This is required
     // because the terminal does not translate keyboard input into interrupts when it
     // is in raw mode. So we watch for that key chord in addition to checking
     // `sigterms_received()`, which can still be triggered by signals from outside
     // this program.
-    let mut stdin = termion::async_stdin().keys();
+    //
+    // Without the UI the terminal stays in cooked mode (and there may be no terminal
+    // at all), so signals are all we need.
+    let mut stdin = if show_ui {
+        Some(termion::async_stdin().keys())
+    } else {
+        None
+    };
 
     // Represents the termination state of the operation. See the documentation on the
     // `TerminationState` enum for more information.
     let mut termination_state = TerminationState::None;
 
-    // The block controls the scope of `stdout`. We put the terminal into raw mode to
-    // display the in-progress UI. When `stdout` goes out of scope, the terminal state
-    // is reset via the destructor.
+    // The block controls the scope of `terminal`. We put the terminal into raw mode
+    // to display the in-progress UI. When `terminal` goes out of scope, the terminal
+    // state is reset via the destructor.
     {
-        let mut stdout = stdout()
-            .into_raw_mode()
-            .expect("failed to put terminal into raw mode");
+        let mut terminal = if show_ui {
+            Some(
+                stdout()
+                    .into_raw_mode()
+                    .expect("failed to put terminal into raw mode"),
+            )
+        } else {
+            None
+        };
 
         // The UI instance controls all output to the terminal while the fetch threads are
         // running. UI code is messy -- so we hide the complexity. That way, the main loop
         // logic isn't cluttered.
-        let mut ui = UI::new(&mut stdout);
-
-        // Initialize `remotes`, `results`, and `ui`.
-        for repo in repo_set {
-            let mut summary = Summary::new();
-            match repo.git().remotes() {
-                Ok(names) => {
-                    for name in names.iter() {
-                        if let Some(name) = name {
-                            remotes.push((repo, name.to_owned()));
-                            ui.push_remote(repo, name);
-                        } else {
-                            summary.push_note(Note::new(
-                                FETCH_FAILURE_GROUP,
-                                Kind::Failure,
-                                "skipped remote with invalid utf-8 name",
-                            ));
-                        }
+        let (color, ascii, theme) = (invocation.color(), invocation.ascii(), invocation.theme());
+        let mut ui = terminal
+            .as_mut()
+            .map(|terminal| UI::new(terminal, color, ascii, theme));
+
+        // Set up the UI, and queue the remotes that are pending.
+        let mut queue = Vec::new();
+        for (repo, name, state) in remotes {
+            if let Some(ref mut ui) = ui {
+                ui.push_remote(repo, &name);
+            }
+            match state {
+                State::Pending => queue.push((repo, name)),
+                state => {
+                    if let Some(ref mut ui) = ui {
+                        ui.update_state(repo, &name, state);
                     }
                 }
-                Err(e) => {
-                    summary.push_note(Note::new(
-                        FETCH_FAILURE_GROUP,
-                        Kind::Failure,
-                        &format!("failed to get remotes ({})", e),
-                    ));
-                }
             }
-            results.insert(repo, summary);
         }
+        let mut remotes = queue;
 
         // `active` keeps track of how many fetch threads are currently running.
         let mut active = 0;
@@ -186,7 +419,7 @@ pub fn run(invocation: &Invocation) {
         //
         // Once `results_rx` receives the message, the main loop assumes the fetch thread
         // is complete, and it will start a new fetch thread.
-        let (results_tx, results_rx) = crossbeam_channel::unbounded();
+        let (results_tx, results_rx) = crossbeam_channel::unbounded::<(&Repo, String, Summary)>();
 
         // Handles to the senders whose receiving ends are in the threads running the `git
         // fetch` subprocesses. If the user wants to hard cancel the fetches, a single
@@ -197,6 +430,7 @@ pub fn run(invocation: &Invocation) {
         // Use crossbeam magic (?) because Rust threading primitives are above my head and
         // this is, like, incredibly clean-looking and appears to work exactly as
         // expected.
+        let job = &job;
         crossbeam::scope(|scope| {
             // Loop until all the current threads are complete and we have nothing left to do.
             while active > 0 || !remotes.is_empty() {
@@ -213,14 +447,19 @@ pub fn run(invocation: &Invocation) {
                         Kind::Failure => State::Failure,
                     };
                     // Notify the UI of the change in state for the remote.
-                    ui.update_state(repo, &(name as String), state);
+                    events.emit(repo, &name, &state, Some(&summary));
+                    if let Some(ref mut ui) = ui {
+                        ui.update_state(repo, &name, state);
+                    }
                     // Free up a thread for use.
                     active -= 1;
                 }
                 // Process any keystrokes, looking for ctrl-c.
-                while let Some(key) = stdin.next() {
-                    if key.expect("failed to parse keyboard input") == Key::Ctrl('c') {
-                        invocation.sigterm_received();
+                if let Some(ref mut stdin) = stdin {
+                    while let Some(key) = stdin.next() {
+                        if key.expect("failed to parse keyboard input") == Key::Ctrl('c') {
+                            invocation.sigterm_received();
+                        }
                     }
                 }
                 // Move to "soft" termination state if we're currently running normally but the
@@ -230,9 +469,14 @@ pub fn run(invocation: &Invocation) {
                     // Drain the pending fetches, setting their state to canceled.
                     while !remotes.is_empty() {
                         let (repo, name) = remotes.remove(0);
-                        ui.update_state(repo, &name, State::Canceled);
+                        events.emit(repo, &name, &State::Canceled, None);
+                        if let Some(ref mut ui) = ui {
+                            ui.update_state(repo, &name, State::Canceled);
+                        }
+                    }
+                    if let Some(ref mut ui) = ui {
+                        ui.cancel(results);
                     }
-                    ui.cancel(&results);
                     termination_state = TerminationState::Soft;
                 }
                 // Move to "hard" termination state if we're currently in "soft" termination state
@@ -252,7 +496,10 @@ pub fn run(invocation: &Invocation) {
                 while active < concurrent && !remotes.is_empty() {
                     let (repo, name) = remotes.remove(0);
                     // Tell the UI we have started the fetch.
-                    ui.update_state(repo, &name, State::Fetching);
+                    events.emit(repo, &name, &State::Fetching, None);
+                    if let Some(ref mut ui) = ui {
+                        ui.update_state(repo, &name, State::Fetching);
+                    }
                     let results_tx = results_tx.clone();
                     let (term_tx, term_rx) = crossbeam_channel::bounded(1);
                     term_txs.push(term_tx);
@@ -260,7 +507,7 @@ pub fn run(invocation: &Invocation) {
                         .builder()
                         .name(format!("{}:{}", repo.name_or_default(), name))
                         .spawn(move |_| {
-                            let summary = fetch_and_ff(&term_rx, repo, &name);
+                            let summary = job(&term_rx, repo, &name);
                             results_tx
                                 .send((repo, name, summary))
                                 .expect("failed to transmit results to main thread");
@@ -270,58 +517,27 @@ pub fn run(invocation: &Invocation) {
                     active += 1;
                 }
                 // Give the UI a chance to update itself.
-                ui.update(&results);
+                if let Some(ref mut ui) = ui {
+                    ui.update(results);
+                }
                 // Rest for a sec before checking all the things again.
                 thread::sleep(t);
             }
         })
         .expect("one or more threads panicked");
         // Tell the UI we are done fetching.
-        ui.cleanup();
-    } // end scope of `stdout`, terminal state should be reset
-
-    // If the user sent two sigterms, assume it signals the intent "get me the hell
-    // out of here as quickly as possible" -- don't bother them with a summary.
-    if termination_state == TerminationState::Hard {
-        println!();
-        return;
-    }
-
-    let header = Style::new().bold().underline();
-    for (tag, repos) in invocation.iter_tags(TAG_ARG) {
-        if let Some(tag) = tag {
-            println!("\n{}{}", header.paint("TAG:"), header.paint(tag));
-        } else {
-            println!();
+        if let Some(ref mut ui) = ui {
+            ui.cleanup();
         }
-        for (name, repo) in repos {
-            let summary = results
-                .get(repo)
-                .expect("failed to look up results for repo");
-            let style = style_for_kind(&summary.kind());
-            println!(
-                "{} {}",
-                style.bold().paint(repo.symbol_or_default()),
-                style.bold().paint(name)
-            );
-            for note in summary.iter() {
-                let style = match *note.kind() {
-                    Kind::None => Style::new(),
-                    Kind::Success => Color::Green.normal(),
-                    Kind::Warning => Color::Yellow.normal(),
-                    Kind::Failure => Color::Red.normal(),
-                };
-                println!("{}", style.paint(format!("  \u{2192} {}", note.message())));
-            }
-        }
-    }
-    println!();
+    } // end scope of `terminal`, terminal state should be reset
+
+    termination_state
 }
 
 // ----- TerminationState -----------------------------------------------------
 
 #[derive(PartialEq)]
-enum TerminationState {
+pub enum TerminationState {
     /// Not termination; running normally.
     None,
     /// Soft termination; allow running fetches to complete, do not start any new ones.
@@ -330,15 +546,125 @@ enum TerminationState {
     Hard,
 }
 
-// ----- style_for_kind -------------------------------------------------------
+// ----- Events ---------------------------------------------------------------
+
+/// Writes a line of JSON to an output stream for each change in the state of a
+/// remote, so that other programs can follow the progress of a pull.
+///
+/// Each event has the `time` (RFC 3339, UTC) of the change, the name of the
+/// `event` (`queued`, `skipped`, `fetching`, `finished` or `canceled`), and the
+/// `repo`, `path` and `remote` it is for. `finished` events also have the `result`
+/// and the `notes` (with a `kind` and `message` each) from the fetch and
+/// fast-forwards. Failures that are not about any one remote get a `finished`
+/// event with a `null` remote.
+///
+/// If writing an event fails (e.g. the consumer has gone away), no more events are
+/// written, so that the fetches can finish. The error is kept for the caller to
+/// report (see `error()`).
+pub struct Events {
+    /// Stream to write events to, or `None` if events are disabled (or writing
+    /// failed).
+    out: Option<Box<dyn Write>>,
+    /// Error from the write that failed, if any.
+    error: Option<String>,
+}
+
+impl Events {
+    /// Creates and returns a new `Events` instance writing to `out` (or not writing
+    /// anything, if `out` is `None`).
+    pub fn new(out: Option<Box<dyn Write>>) -> Self {
+        Self { out, error: None }
+    }
+
+    /// Returns the error from writing events, if writing failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Writes an event for the remote named `remote` of `repo` changing to `state`,
+    /// including the notes from `summary` if supplied.
+    fn emit(&mut self, repo: &Repo, remote: &str, state: &State, summary: Option<&Summary>) {
+        self.write(repo, Some(remote), state, summary);
+    }
 
-/// Returns the "standard" `Style` for the given `kind`.
-fn style_for_kind(kind: &Kind) -> Style {
-    match *kind {
-        Kind::None => Style::new(),
-        Kind::Success => Color::Green.normal(),
-        Kind::Warning => Color::Yellow.normal(),
-        Kind::Failure => Color::Red.normal(),
+    /// Writes a `finished` event with a `failure` result for `repo` as a whole (the
+    /// `remote` is `null`), including the notes from `summary`. This is for failures
+    /// that are not about any one remote, like failing to get the remotes.
+    fn emit_failure(&mut self, repo: &Repo, summary: &Summary) {
+        self.write(repo, None, &State::Failure, Some(summary));
+    }
+
+    /// Writes an event for `remote` (or the repo as a whole, if `None`) of `repo`
+    /// changing to `state`, including the notes from `summary` if supplied.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn write(
+        &mut self,
+        repo: &Repo,
+        remote: Option<&str>,
+        state: &State,
+        summary: Option<&Summary>,
+    ) {
+        let out = match self.out {
+            Some(ref mut out) => out,
+            None => return,
+        };
+        let (event, result) = match *state {
+            State::Pending => ("queued", None),
+            State::Skipped => ("skipped", None),
+            State::Canceled => ("canceled", None),
+            State::Fetching => ("fetching", None),
+            State::NoChange => ("finished", Some("no_change")),
+            State::Success => ("finished", Some("success")),
+            State::Warning => ("finished", Some("warning")),
+            State::Failure => ("finished", Some("failure")),
+        };
+        let mut line = format!(
+            "{{\"time\":{},\"event\":{},\"repo\":{},\"path\":{},\"remote\":{}",
+            json_string(&format_timestamp(SystemTime::now())),
+            json_string(event),
+            json_string(repo.name_or_default()),
+            json_string(repo.full_path()),
+            remote.map_or(String::from("null"), json_string),
+        );
+        if let Some(result) = result {
+            line.push_str(&format!(",\"result\":{}", json_string(result)));
+        }
+        if let Some(summary) = summary {
+            let notes = summary
+                .iter()
+                .map(|note| {
+                    format!(
+                        "{{\"kind\":{},\"message\":{}}}",
+                        json_string(note.kind().name()),
+                        json_string(note.message())
+                    )
+                })
+                .collect::<Vec<String>>();
+            line.push_str(&format!(",\"notes\":[{}]", notes.join(",")));
+        }
+        line.push('}');
+        if let Err(e) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+            self.out = None;
+            self.error = Some(e.to_string());
+        }
+    }
+}
+
+// ----- fetched_within -------------------------------------------------------
+
+/// Returns the number of seconds since the remote named `name` was last fetched in
+/// `git`, if that is less than `seconds`. Otherwise (including if the remote has
+/// never been fetched), returns `None`.
+fn fetched_within(git: &Repository, name: &str, seconds: u64) -> Option<u64> {
+    let age = SystemTime::now()
+        .duration_since(last_fetched(git, name)?)
+        .map(|age| age.as_secs())
+        .unwrap_or(0);
+    if age < seconds {
+        Some(age)
+    } else {
+        None
     }
 }
 
@@ -384,80 +710,11 @@ fn style_for_kind(kind: &Kind) -> Style {
 /// Technically, I guess the git executable might not be present (and the code does
 /// not handle this case). But, seriously, who's using mgit that doesn't have git
 /// installed and on the PATH? (Those sound an awful lot like famous last words.)
-#[allow(clippy::cast_possible_wrap)]
 fn fetch_and_ff(term_rx: &Receiver<bool>, repo: &Repo, name: &str) -> Summary {
-    // The `git fetch` subprocess can spawn its own subprocesses. If we need to kill
-    // `git fetch` we want to kill all its children as well. To do so, we make sure
-    // `git fetch` and its children all have the same process group id (which we make
-    // sure is different than the parent process' pgid), then use `killpg(pgid)` to
-    // kill the children without touching the parent.
-    //
-    // By default children inherit the same pgid as the parent, so setting the right
-    // pgid for the `git fetch` means its children will also have the correct value.
-    //
-    // We use `before_exec` to set the pgid for `git fetch`. Per the documentation,
-    // `before_exec` runs after the process fork, so the child will have a new, unique
-    // pid. When `setpgid(pid, pgid)` is called with a 0 for the first argument, the
-    // call applies to the calling process (our child). When pgid is 0, the pgid is
-    // set to the same value as the pid.
-    let mut child = Command::new("git")
-        .args(&["fetch", name])
-        .current_dir(repo.full_path())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .before_exec(|| {
-            let pid_zero = nix::unistd::Pid::from_raw(0);
-            nix::unistd::setpgid(pid_zero, pid_zero).expect("failed to set process group id");
-            Ok(())
-        })
-        .spawn()
-        .expect("failed to start `git fetch` command");
-
-    // Periodically check whether the process has exited, or whether the mgit has
-    // received a sigterm (in which case the child processes are killed and an empty
-    // summary returned immediately).
-    let t = Duration::from_millis(1000 / UPDATE_FREQUENCY);
-    while None
-        == child
-            .try_wait()
-            .expect("failed to get status of child process")
-    {
-        if term_rx.try_recv().is_ok() {
-            // NOTE: nix does not currently implement killpg (see
-            //       https://github.com/nix-rust/nix/issues/644)
-            // let pgid = nix::unistd::Pid::from_raw(child.id() as i32);
-            // let signal = Some(nix::sys::signal::Signal::SIGKILL);
-            // nix::sys::signal::killpg(pgid, signal).expect_or_else(|| {
-            //     &format!("failed to kill process group: {}", child.id() as i32,)
-            // });
-            unsafe {
-                assert_eq!(0, libc::killpg(child.id() as i32, 9));
-            }
-            return Summary::new();
-        }
-        thread::sleep(t);
-    }
-
-    // Make a final blocking call (which shouldn't actually block) to get the output
-    // from the command and determine whether it completed successfully.
-    let error = match child.wait_with_output() {
-        Ok(out) => {
-            if out.status.success() {
-                None
-            } else {
-                let stdout = String::from_utf8_lossy(&out.stdout);
-                let stderr = String::from_utf8_lossy(&out.stderr);
-                let rv = if stdout.len() > 0 && stderr.len() > 0 {
-                    format!("STDOUT:\n{}\nSTDERR:\n{}", stdout, stderr)
-                } else if stdout.len() > 0 {
-                    stdout.into_owned()
-                } else {
-                    stderr.into_owned()
-                };
-                Some(rv)
-            }
-        }
-        Err(e) => Some(format!("{}", e)),
+    let error = match run_git(term_rx, repo.full_path(), &["fetch", name]) {
+        Some(Ok(())) => None,
+        Some(Err(message)) => Some(message),
+        None => return Summary::new(),
     };
 
     let git = repo.git();
@@ -616,15 +873,103 @@ fn fetch_and_ff(term_rx: &Receiver<bool>, repo: &Repo, name: &str) -> Summary {
     summary
 }
 
+// ----- run_git --------------------------------------------------------------
+
+/// Runs git with the arguments `args` in the directory `dir`, and returns the
+/// result: `Ok(())` if it exited successfully, or the output of the command if it
+/// did not. If a message is received on `term_rx` while git is running, git (and
+/// any processes it started) is killed, and `None` is returned.
+#[allow(clippy::cast_possible_wrap)]
+pub fn run_git(term_rx: &Receiver<bool>, dir: &str, args: &[&str]) -> Option<Result<(), String>> {
+    // The git subprocess can spawn its own subprocesses. If we need to kill git we
+    // want to kill all its children as well. To do so, we make sure git and its
+    // children all have the same process group id (which we make sure is different
+    // than the parent process' pgid), then use `killpg(pgid)` to kill the children
+    // without touching the parent.
+    //
+    // By default children inherit the same pgid as the parent, so setting the right
+    // pgid for git means its children will also have the correct value.
+    //
+    // We use `before_exec` to set the pgid for git. Per the documentation,
+    // `before_exec` runs after the process fork, so the child will have a new, unique
+    // pid. When `setpgid(pid, pgid)` is called with a 0 for the first argument, the
+    // call applies to the calling process (our child). When pgid is 0, the pgid is
+    // set to the same value as the pid.
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .before_exec(|| {
+            let pid_zero = nix::unistd::Pid::from_raw(0);
+            nix::unistd::setpgid(pid_zero, pid_zero).expect("failed to set process group id");
+            Ok(())
+        })
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to start `git {}` command: {}", args[0], e));
+
+    // Periodically check whether the process has exited, or whether the mgit has
+    // received a sigterm (in which case the child processes are killed and `None`
+    // returned immediately).
+    let t = Duration::from_millis(1000 / UPDATE_FREQUENCY);
+    while None
+        == child
+            .try_wait()
+            .expect("failed to get status of child process")
+    {
+        if term_rx.try_recv().is_ok() {
+            // NOTE: nix does not currently implement killpg (see
+            //       https://github.com/nix-rust/nix/issues/644)
+            // let pgid = nix::unistd::Pid::from_raw(child.id() as i32);
+            // let signal = Some(nix::sys::signal::Signal::SIGKILL);
+            // nix::sys::signal::killpg(pgid, signal).expect_or_else(|| {
+            //     &format!("failed to kill process group: {}", child.id() as i32,)
+            // });
+            unsafe {
+                assert_eq!(0, libc::killpg(child.id() as i32, 9));
+            }
+            return None;
+        }
+        thread::sleep(t);
+    }
+
+    // Make a final blocking call (which shouldn't actually block) to get the output
+    // from the command and determine whether it completed successfully.
+    let result = match child.wait_with_output() {
+        Ok(out) => {
+            if out.status.success() {
+                Ok(())
+            } else {
+                let stdout = String::from_utf8_lossy(&out.stdout);
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                let rv = if stdout.len() > 0 && stderr.len() > 0 {
+                    format!("STDOUT:\n{}\nSTDERR:\n{}", stdout, stderr)
+                } else if stdout.len() > 0 {
+                    stdout.into_owned()
+                } else {
+                    stderr.into_owned()
+                };
+                Err(rv)
+            }
+        }
+        Err(e) => Err(format!("{}", e)),
+    };
+    Some(result)
+}
+
 // ----- State ----------------------------------------------------------------
 
 /// Represents the state of the fetch/fast-forward for a remote.
 #[derive(Clone, Debug)]
-enum State {
+pub enum State {
     /// Fetch has not yet started.
     Pending,
     /// Fetch has been canceled by the user.
     Canceled,
+    /// Fetch was skipped because the remote was fetched recently (see
+    /// `--older-than`), or was not selected (see `-r/--remote` and
+    /// `Repo::fetches()`).
+    Skipped,
     /// Fetch is in progress.
     Fetching,
     /// Fetch was successful, no tracking branches were ahead or behind.
@@ -639,6 +984,22 @@ enum State {
     Failure,
 }
 
+impl State {
+    /// Returns the name of the state, as used in the theme (see `theme::STATES`).
+    fn name(&self) -> &'static str {
+        match *self {
+            State::Pending => "pending",
+            State::Canceled => "canceled",
+            State::Skipped => "skipped",
+            State::Fetching => "fetching",
+            State::NoChange => "no-change",
+            State::Success => "success",
+            State::Warning => "warning",
+            State::Failure => "failure",
+        }
+    }
+}
+
 // ----- UI -------------------------------------------------------------------
 
 /// Manages the user interface during fetch and fast-forward.
@@ -653,20 +1014,8 @@ struct UI<'a, W: 'a + Write> {
     canceled: bool,
     /// `RawTerminal` instance on which all drawing commands are done.
     t: &'a mut RawTerminal<W>,
-    /// Width and height of the drawn UI.
-    drawn: (u16, u16),
-    /// Holds the terminal resize debounce state.
-    ///
-    /// Every iteration of the main loop (inside the call to `update`), we check the
-    /// terminal size. When terminal size changes from the `drawn` size, this gets set
-    /// to `Some(<new-width>, <new-height>, Instant::now())`.
-    ///
-    /// Subsequent updates will continue to check terminal size. If it changes again, a
-    /// new `Some(w, h, Instant::now())` value is generated. If `DEBOUNCE_MILLIS` goes
-    /// by without a change, mgit assumes the user is done resizing and redraws the UI
-    /// based on the new terminal size. (Debounce is then set to `None` as we are done
-    /// debouncing.)
-    debounce: Option<(u16, u16, Instant)>,
+    /// Tracks the drawn size of the UI and debounces terminal resizes.
+    resize: ResizeDebounce,
     /// Cache of all strings drawn to the screen as well as their location, keyed by
     /// `&Repo` and optionally remote name (a `String`).
     ///
@@ -678,19 +1027,29 @@ struct UI<'a, W: 'a + Write> {
     /// Otherwise the key will be `(&Repo, Some(String))` where the string represents
     /// the name of the remote.
     locations: HashMap<(&'a Repo, Option<String>), (u16, u16, String)>,
+    /// Indicates whether to use colors.
+    color: bool,
+    /// Indicates whether to use only ASCII characters.
+    ascii: bool,
+    /// Theme for styles and glyphs.
+    theme: &'a Theme,
 }
 
 impl<'a, W: Write> UI<'a, W> {
-    /// Creates and returns a new `UI` instance.
-    fn new(terminal: &'a mut RawTerminal<W>) -> Self {
+    /// Creates and returns a new `UI` instance, drawing with `theme`. If `color` is
+    /// `false`, everything is drawn in the default style. If `ascii` is `true`,
+    /// only ASCII characters are drawn (other than those in repo and remote names).
+    fn new(terminal: &'a mut RawTerminal<W>, color: bool, ascii: bool, theme: &'a Theme) -> Self {
         Self {
             state: HashMap::new(),
             updates: Vec::new(),
             canceled: false,
             t: terminal,
-            drawn: (0, 0),
-            debounce: None,
+            resize: ResizeDebounce::new(),
             locations: HashMap::new(),
+            color,
+            ascii,
+            theme,
         }
     }
 
@@ -713,24 +1072,10 @@ impl<'a, W: Write> UI<'a, W> {
 
     /// Instructs the user interface to update the terminal.
     fn update(&mut self, results: &Results) {
-        let (w, h) = termion::terminal_size().expect("failed to get terminal size");
-        let debounce = Some((w, h, Instant::now()));
-        let (drawn_w, drawn_h) = self.drawn;
-        if drawn_w == 0 && drawn_h == 0 {
-            self.draw(w, h, results);
-        } else if let Some((new_w, new_h, t)) = self.debounce {
-            if w == new_w && h == new_h {
-                if t.elapsed() >= Duration::from_millis(DEBOUNCE_MILLIS) {
-                    self.debounce = None;
-                    self.draw(w, h, results);
-                }
-            } else {
-                self.debounce = debounce;
-            }
-        } else if w != drawn_w || h != drawn_h {
-            self.debounce = debounce;
-        } else {
-            self.process_updates(results);
+        match self.resize.check() {
+            Resize::Draw(w, h) => self.draw(w, h, results),
+            Resize::Pending => {}
+            Resize::Unchanged => self.process_updates(results),
         }
     }
 
@@ -763,6 +1108,12 @@ impl<'a, W: Write> UI<'a, W> {
         // assign them some variables.
         let (w_usize, h_usize) = (w as usize, h as usize);
 
+        // The marker for truncated lines goes at the far right, and takes up `e`
+        // columns (more than one in ASCII mode).
+        let marker = ellipsis(self.ascii);
+        let e = marker.chars().count();
+        let marker_x = (w_usize.saturating_sub(e) + 1) as u16;
+
         // Clear the screen, and the current state of what's drawn where.
         self.locations.clear();
         write!(self.t, "{}", clear::All).expect("failed to write content to the terminal");
@@ -813,13 +1164,13 @@ impl<'a, W: Write> UI<'a, W> {
                         // Number not displayed is overflow + 1, because we are also not displaying
                         // *this* repo.
                         let mut message = format!("\u{2026}{} more not shown", overflow_h + 1);
+                        if self.ascii {
+                            message = to_ascii(&message);
+                        }
                         // Our message might be longer than the available width. If so, truncate it
                         // and add an ellipsis at the end.
-                        if message.len() > w_usize {
-                            message.truncate(w_usize - 1);
-                            message.push_str("\u{2026}");
-                        }
-                        write!(self.t, "{}{}", cursor::Goto(1, y), message)
+                        let line = truncate(&[(Style::new(), message)], w_usize, self.ascii);
+                        write!(self.t, "{}{}", cursor::Goto(1, y), paint(&line))
                             .expect("failed to write content to the terminal");
                     }
                     break;
@@ -840,11 +1191,11 @@ impl<'a, W: Write> UI<'a, W> {
                     remaining -= 1;
                 }
 
-                // We need at least two characters to draw a repo name (the first character plus
-                // an ellipsis). If we don't have two, draw an ellipsis at the far right and bail
-                // out of this loop iteration.
-                if remaining < 2 {
-                    write!(self.t, "{}\u{2026}", cursor::Goto(w, y))
+                // We need at least the first character of a repo name plus an ellipsis. If we
+                // don't have that, draw an ellipsis at the far right and bail out of this loop
+                // iteration.
+                if remaining < 1 + e {
+                    write!(self.t, "{}{}", cursor::Goto(marker_x, y), marker)
                         .expect("failed to write content to the terminal");
                     continue;
                 }
@@ -853,10 +1204,10 @@ impl<'a, W: Write> UI<'a, W> {
                 let mut needs_ellipsis = false;
 
                 // If the repo name "runs past the right of the terminal," truncate it to the
-                // terminal width minus one (where the one is reserved for an ellipsis).
-                let (name, n) = if n >= remaining {
+                // terminal width minus the width of the ellipsis.
+                let (name, n) = if n > remaining - e {
                     needs_ellipsis = true;
-                    let s = &name[..remaining - 1];
+                    let s = &name[..remaining - e];
                     (s, s.len())
                 } else {
                     (name, n)
@@ -867,7 +1218,7 @@ impl<'a, W: Write> UI<'a, W> {
                     .get(*repo)
                     .expect("failed to get summary for repo")
                     .kind();
-                let style = style_for_kind(&kind).bold();
+                let style = self.style(self.theme.style_for_kind(&kind).bold());
                 line.push_str(&format!("{}", style.paint(name)));
 
                 // Store the location and string we just painted.
@@ -890,10 +1241,14 @@ impl<'a, W: Write> UI<'a, W> {
                     .collect();
                 remote_names.sort();
 
+                // If the theme has glyphs for states, each remote name is preceded by one.
+                let g = if self.theme.has_state_glyphs() { 1 } else { 0 };
+
                 for full_name in remote_names {
-                    // We need three characters to draw the remote (one for the space, one for the
-                    // first character, one for the ellipsis). If we don't have three, bail.
-                    if remaining < 3 {
+                    // We need one character for the space and one for the first character of
+                    // the remote, plus the glyph and the ellipsis. If we don't have that many,
+                    // bail.
+                    if remaining < 2 + g + e {
                         needs_ellipsis = true;
                         break;
                     }
@@ -901,11 +1256,11 @@ impl<'a, W: Write> UI<'a, W> {
                     let n = full_name.len();
 
                     // If the remote name plus one (the space to the left) "runs past the right of
-                    // the terminal," truncate it to the terminal width minus two (where one
-                    // character is reserved for the space and the other for the ellipsis).
-                    let (name, n) = if n + 1 >= remaining {
+                    // the terminal," truncate it to what is left after the space, the glyph and
+                    // the ellipsis.
+                    let (name, n) = if n + 1 + g > remaining - e {
                         needs_ellipsis = true;
-                        let s = &full_name[..remaining - 2];
+                        let s = &full_name[..remaining - 1 - g - e];
                         (s, s.len())
                     } else {
                         (full_name.as_str(), n)
@@ -918,7 +1273,7 @@ impl<'a, W: Write> UI<'a, W> {
                         .expect("failed to get repo value from state")
                         .get(full_name)
                         .expect("failed to get state for remote");
-                    line.push_str(&format!(" {}", self.style_for_state(state).paint(name),));
+                    line.push_str(&format!(" {}", self.paint_state(state, name)));
 
                     // Store the location and string we just painted.
                     let x = w - ((remaining - 2) as u16);
@@ -929,11 +1284,11 @@ impl<'a, W: Write> UI<'a, W> {
 
                     // Reduce the remaining characters by the number of characters that we just
                     // drew into the line.
-                    remaining -= n + 1;
+                    remaining -= n + 1 + g;
                 }
 
                 if needs_ellipsis {
-                    write!(self.t, "{}\u{2026}", cursor::Goto(w, y))
+                    write!(self.t, "{}{}", cursor::Goto(marker_x, y), marker)
                         .expect("failed to write content to the terminal");
                 }
 
@@ -943,23 +1298,19 @@ impl<'a, W: Write> UI<'a, W> {
             }
 
             if self.canceled {
-                let mut message = "pending fetches canceled; allowing in-flight fetches to finish \
-                                   (hit Ctrl-c again to terminate unsafely)"
+                let message = "pending fetches canceled; allowing in-flight fetches to finish \
+                               (hit Ctrl-c again to terminate unsafely)"
                     .to_string();
-                if message.len() > w_usize {
-                    message.truncate(w_usize - 1);
-                    message.push_str("\u{2026}");
-                }
-                write!(
-                    self.t,
-                    "{}{}",
-                    cursor::Goto(1, y + 1),
-                    Color::Red.bold().paint(message)
-                )
-                .expect("failed to write content to the terminal");
+                let line = truncate(
+                    &[(self.style(Color::Red.bold()), message)],
+                    w_usize,
+                    self.ascii,
+                );
+                write!(self.t, "{}{}", cursor::Goto(1, y + 1), paint(&line))
+                    .expect("failed to write content to the terminal");
             }
         }
-        self.drawn = (w, h);
+        self.resize.drawn(w, h);
         self.process_updates(results);
     }
 
@@ -970,15 +1321,19 @@ impl<'a, W: Write> UI<'a, W> {
     fn process_updates(&mut self, results: &Results) {
         for &(repo, ref remote, ref state) in &self.updates {
             if let Some(&(x, y, ref s)) = self.locations.get(&(repo, Some(remote.to_owned()))) {
-                let style = self.style_for_state(state);
-                write!(self.t, "{}{}", cursor::Goto(x, y), style.paint(s.as_str()))
-                    .expect("failed to write content to the terminal");
+                write!(
+                    self.t,
+                    "{}{}",
+                    cursor::Goto(x, y),
+                    self.paint_state(state, s)
+                )
+                .expect("failed to write content to the terminal");
             }
             if let Some(&(x, y, ref s)) = self.locations.get(&(repo, None)) {
                 let summary = results
                     .get(&repo)
                     .expect("failed to get repo from results cache");
-                let style = style_for_kind(&summary.kind()).bold();
+                let style = self.style(self.theme.style_for_kind(&summary.kind()).bold());
                 write!(self.t, "{}{}", cursor::Goto(x, y), style.paint(s.as_str()))
                     .expect("failed to write content to the terminal");
             }
@@ -994,18 +1349,35 @@ impl<'a, W: Write> UI<'a, W> {
             .expect("failed to flush content to the terminal");
     }
 
-    /// Returns the appropriate style for the given `state`.
+    /// Returns `name` (of a remote) painted in the style for `state`, preceded by
+    /// the glyph for `state` if the theme has glyphs for states.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn paint_state(&self, state: &State, name: &str) -> String {
+        let mut glyph = if self.theme.has_state_glyphs() {
+            self.theme
+                .glyph_for_state(state.name())
+                .unwrap_or(" ")
+                .to_owned()
+        } else {
+            String::new()
+        };
+        if self.ascii {
+            // The glyph has to stay a single character wide.
+            glyph = to_ascii_glyph(&glyph).chars().take(1).collect();
+        }
+        let style = self.style(self.theme.style_for_state(state.name()));
+        format!("{}", style.paint(format!("{}{}", glyph, name)))
+    }
+
+    /// Returns `style`, or the default (plain) style if colors are turned off.
     ///
     /// **This is an internal method and should not be called outside the impl.**
-    fn style_for_state(&self, state: &State) -> Style {
-        match *state {
-            State::Pending => Color::Blue.normal(),
-            State::Canceled => Style::new().dimmed(),
-            State::Fetching => Color::Cyan.normal(),
-            State::NoChange => Style::new(),
-            State::Success => Color::Green.normal(),
-            State::Warning => Color::Yellow.normal(),
-            State::Failure => Color::Red.normal(),
+    fn style(&self, style: Style) -> Style {
+        if self.color {
+            style
+        } else {
+            Style::new()
         }
     }
 }
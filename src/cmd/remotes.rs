@@ -0,0 +1,221 @@
+//! `remotes` subcommand.
+use std::collections::BTreeMap;
+
+use clap::Arg;
+
+use app::{negation, Field, Invocation, Repo};
+use render::{each_repo, Emphasis};
+use ui::{Kind, Note, Summary};
+
+/// Name of the command (`remotes`).
+pub const NAME: &str = "remotes";
+/// One-line description of the command (`remotes`).
+pub const ABOUT: &str = "Compares remotes of repositories with the configuration";
+/// This is not a "simple" command. With `--fix`, being killed suddenly could leave
+/// the remotes of a repo half-updated.
+pub const EXIT_ON_SIGTERM: bool = false;
+/// mgit warns about repos that are not cloned yet before running this command.
+pub const WARN_UNCLONED: bool = true;
+
+/// Name of the argument for `--fix`.
+const FIX_ARG: &str = "FIX";
+/// Name of the argument for `--no-fix`.
+const NO_FIX_ARG: &str = "NO_FIX";
+/// Name of the argument for `--prune`.
+const PRUNE_ARG: &str = "PRUNE";
+/// Name of the argument for `--no-prune`.
+const NO_PRUNE_ARG: &str = "NO_PRUNE";
+/// Name of the argument for `-t/--tag`.
+const TAG_ARG: &str = "TAG";
+
+/// Group number for errors encountered when reading or changing remotes.
+const REMOTE_FAILURE_GROUP: usize = 0;
+/// Group number for remotes that are missing from the repo.
+const REMOTE_MISSING_GROUP: usize = 1;
+/// Group number for remotes with a different URL than configured.
+const REMOTE_MISMATCH_GROUP: usize = 2;
+/// Group number for remotes that are not in the configuration.
+const REMOTE_EXTRA_GROUP: usize = 3;
+
+/// Returns the arguments for the command.
+pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name(FIX_ARG)
+            .help("Adds missing remotes and updates the URLs of mismatched remotes")
+            .long("fix"),
+        negation(NO_FIX_ARG, "no-fix", FIX_ARG),
+        Arg::with_name(PRUNE_ARG)
+            .help("Also removes remotes that are not in the configuration")
+            .long("prune")
+            .requires(FIX_ARG),
+        negation(NO_PRUNE_ARG, "no-prune", PRUNE_ARG),
+        Arg::with_name(TAG_ARG)
+            .help("Limits/groups display to repos with specified tag(s)")
+            .short("t")
+            .long("tag")
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
+
+/// Executes the `remotes` subcommand.
+///
+/// Only repos with `remote.<name>` keys in the configuration (see
+/// `Repo::declares_remotes()`) are checked, since for the others every remote would
+/// be extra. A `url` on its own only says where to clone a repo from. If mgit
+/// receives a termination signal, the remaining repos are left alone, and only the
+/// ones done so far are shown.
+pub fn run(invocation: &Invocation) {
+    let fix = invocation.is_present(FIX_ARG);
+    // `--prune` requires `--fix`, but only on the command line: `--no-fix` turns off
+    // both if they're set in the configuration.
+    let prune = fix && invocation.is_present(PRUNE_ARG);
+    let mut renderer = invocation.format().renderer(invocation, Emphasis::RESULTS);
+    each_repo(
+        invocation,
+        TAG_ARG,
+        Field::Name,
+        &mut *renderer,
+        |renderer, repo, name| {
+            if invocation.sigterms_received() == 0 && repo.declares_remotes() {
+                renderer.repo(repo, name, &reconcile(repo, fix, prune));
+            }
+        },
+    );
+    print!("{}", renderer.finish());
+}
+
+// ----- reconcile ------------------------------------------------------------
+
+/// Compares the remotes of `repo` with the configured remotes, and returns a
+/// summary of the differences: remotes that are missing, have a different URL, or
+/// are not in the configuration (extra).
+///
+/// If `fix` is `true`, missing remotes are added and mismatched URLs updated. If
+/// `prune` is also `true`, extra remotes are removed.
+fn reconcile(repo: &Repo, fix: bool, prune: bool) -> Summary {
+    let mut summary = Summary::new();
+    let git = repo.git();
+
+    // Maps the name of each remote in the repo to its URL, so they can be compared
+    // with the configured remotes.
+    let mut actual = BTreeMap::new();
+    match git.remotes() {
+        Ok(names) => {
+            for name in names.iter() {
+                let name = match name {
+                    Some(name) => name,
+                    None => {
+                        summary.push_note(Note::new(
+                            REMOTE_FAILURE_GROUP,
+                            Kind::Failure,
+                            "skipped remote with invalid utf-8 name",
+                        ));
+                        continue;
+                    }
+                };
+                match git.find_remote(name) {
+                    Ok(remote) => {
+                        actual.insert(name.to_owned(), remote.url().map(|url| url.to_owned()));
+                    }
+                    Err(e) => summary.push_note(Note::new(
+                        REMOTE_FAILURE_GROUP,
+                        Kind::Failure,
+                        &format!("failed to look up remote {} ({})", name, e.message()),
+                    )),
+                }
+            }
+        }
+        Err(e) => {
+            summary.push_note(Note::new(
+                REMOTE_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to get remotes ({})", e.message()),
+            ));
+            return summary;
+        }
+    }
+
+    for (name, url) in repo.remotes() {
+        match actual.remove(name) {
+            None => {
+                let note = if !fix {
+                    Note::new(
+                        REMOTE_MISSING_GROUP,
+                        Kind::Warning,
+                        &format!("missing remote {} ({})", name, url),
+                    )
+                } else {
+                    match git.remote(name, url) {
+                        Ok(_) => Note::new(
+                            REMOTE_MISSING_GROUP,
+                            Kind::Success,
+                            &format!("added remote {} ({})", name, url),
+                        ),
+                        Err(e) => Note::new(
+                            REMOTE_FAILURE_GROUP,
+                            Kind::Failure,
+                            &format!("failed to add remote {} ({})", name, e.message()),
+                        ),
+                    }
+                };
+                summary.push_note(note);
+            }
+            Some(ref actual_url) if actual_url.as_ref().map(|s| s.as_str()) == Some(url) => {}
+            Some(actual_url) => {
+                let actual_url = actual_url.unwrap_or_else(|| String::from("<invalid utf-8>"));
+                let note = if !fix {
+                    Note::new(
+                        REMOTE_MISMATCH_GROUP,
+                        Kind::Warning,
+                        &format!("remote {} is {}, expected {}", name, actual_url, url),
+                    )
+                } else {
+                    match git.remote_set_url(name, url) {
+                        Ok(()) => Note::new(
+                            REMOTE_MISMATCH_GROUP,
+                            Kind::Success,
+                            &format!(
+                                "changed url of remote {} from {} to {}",
+                                name, actual_url, url
+                            ),
+                        ),
+                        Err(e) => Note::new(
+                            REMOTE_FAILURE_GROUP,
+                            Kind::Failure,
+                            &format!("failed to change url of remote {} ({})", name, e.message()),
+                        ),
+                    }
+                };
+                summary.push_note(note);
+            }
+        }
+    }
+
+    // Whatever is left in `actual` is not in the configuration.
+    for (name, url) in actual {
+        let url = url.unwrap_or_else(|| String::from("<invalid utf-8>"));
+        let note = if !prune {
+            Note::new(
+                REMOTE_EXTRA_GROUP,
+                Kind::Warning,
+                &format!("extra remote {} ({})", name, url),
+            )
+        } else {
+            match git.remote_delete(&name) {
+                Ok(()) => Note::new(
+                    REMOTE_EXTRA_GROUP,
+                    Kind::Success,
+                    &format!("removed remote {} ({})", name, url),
+                ),
+                Err(e) => Note::new(
+                    REMOTE_FAILURE_GROUP,
+                    Kind::Failure,
+                    &format!("failed to remove remote {} ({})", name, e.message()),
+                ),
+            }
+        };
+        summary.push_note(note);
+    }
+    summary
+}
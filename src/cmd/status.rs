@@ -1,29 +1,95 @@
 //! `status` subcommand.
-use std::collections::HashMap;
+use std::{
+    cmp,
+    collections::HashMap,
+    io::{stdout, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use ansi_term::{Color, Style};
+use ansi_term::Style;
 use clap::Arg;
-use git2::{Status, StatusOptions, StatusShow};
+use crossbeam;
+use crossbeam_channel;
+use git2::{DiffDelta, Repository, RepositoryState, Status, StatusOptions, StatusShow, Statuses};
+use termion::{self, clear, cursor, event::Key, input::TermRead, raw::IntoRawMode};
+use walkdir::WalkDir;
 
-use app::{Invocation, Repo};
-use ui::{Kind, Note, Summary, TrackingBranches};
+use app::{negation, Error, Field, Invocation, Repo};
+use render::{each_repo, paint, render, text, truncate, Emphasis, Line, Renderer, Text};
+use theme::Theme;
+use ui::{
+    concurrent, format_duration, last_fetched, parse_duration, to_ascii, Kind, Note, Resize,
+    ResizeDebounce, Summary, TrackingBranches,
+};
+use watch::{Watch, Watcher};
 
 /// Name of the command (`status`).
 pub const NAME: &str = "status";
 /// One-line description of the command (`status`).
 pub const ABOUT: &str = "Prints current status of repositories";
-/// This is a "simple" command.
-pub const EXIT_ON_SIGTERM: bool = true;
+/// This is not a "simple" command. In watch mode the terminal is in raw mode, which
+/// has to be reset before exiting. Either way, mgit still exits with `1` on a
+/// termination signal (see `Control::interrupted()`).
+pub const EXIT_ON_SIGTERM: bool = false;
+/// mgit warns about repos that are not cloned yet before running this command.
+pub const WARN_UNCLONED: bool = true;
 
+/// Name of the argument for `-c/--concurrent`.
+const CONCURRENT_ARG: &str = "CONCURRENT";
+/// Default number of repos for which to concurrently compute status.
+const CONCURRENT_DEFAULT: &str = "8";
+/// Name of the argument for `-f/--files`.
+const FILES_ARG: &str = "FILES";
+/// Name of the argument for `--no-files`.
+const NO_FILES_ARG: &str = "NO_FILES";
+/// Name of the argument for `--max-files`.
+const MAX_FILES_ARG: &str = "MAX_FILES";
+/// Default maximum number of files to list per repo.
+const MAX_FILES_DEFAULT: &str = "20";
+/// Name of the argument for `-s/--short`.
+const SHORT_ARG: &str = "SHORT";
+/// Name of the argument for `--no-short`.
+const NO_SHORT_ARG: &str = "NO_SHORT";
+/// Name of the argument for `--stale`.
+const STALE_ARG: &str = "STALE";
 /// Name of the argument for `-t/--tag`.
 const TAG_ARG: &str = "TAG";
 /// Name of the argument for `-v/--verbose`.
 const VERBOSE_ARG: &str = "VERBOSE";
+/// Name of the argument for `--no-verbose`.
+const NO_VERBOSE_ARG: &str = "NO_VERBOSE";
+/// Name of the argument for `-w/--watch`.
+const WATCH_ARG: &str = "WATCH";
+/// Name of the argument for `--no-watch`.
+const NO_WATCH_ARG: &str = "NO_WATCH";
+
+/// Number of times per second to check for changes and update the UI in watch
+/// mode.
+const UPDATE_FREQUENCY: u64 = 10;
+/// Number of milliseconds without filesystem events after which changes to a repo
+/// are considered "settled" (and its status is recomputed) in watch mode. This
+/// keeps bursts of changes, like a branch checkout, from causing a recompute for
+/// every single file.
+const WATCH_DEBOUNCE_MILLIS: u64 = 300;
+/// Number of seconds between recomputing the status of repos that could not be
+/// watched for changes (e.g. because the limit on the number of watches was
+/// reached) in watch mode.
+const WATCH_POLL_SECONDS: u64 = 5;
 
 /// Group number for errors encountered when fetching statuses.
 const STATUS_FAILURE_GROUP: usize = 0;
 /// Group number for errors encountered when getting branch status.
 const BRANCH_FAILURE_GROUP: usize = 1;
+/// Group number for in-progress operations (merge, rebase, etc) and detached HEAD.
+const STATE_FAILURE_GROUP: usize = 2;
+/// Group number for errors encountered when inspecting stashes and worktrees.
+const EXTRA_FAILURE_GROUP: usize = 3;
+
+/// Group number for files with conflicts.
+const STATUS_CONFLICTED_GROUP: usize = 9;
 
 /// Group number for files that are changed in index but uncommitted.
 const STATUS_INDEXED_GROUP: usize = 10;
@@ -32,12 +98,44 @@ const STATUS_MODIFIED_GROUP: usize = 11;
 /// Group number for untracked files.
 const STATUS_UNTRACKED_GROUP: usize = 12;
 
+/// Group number for stash entries.
+const STASH_GROUP: usize = 20;
+/// Group number for linked worktrees.
+const WORKTREE_GROUP: usize = 21;
+
+/// Group number for the time since each remote was last fetched.
+const FETCH_GROUP: usize = 100;
+
 /// Group number for branch status messages.
 const BRANCH_STATUS_GROUP: usize = 110;
 
 /// Returns the arguments for the command.
 pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
     vec![
+        Arg::with_name(CONCURRENT_ARG)
+            .default_value(CONCURRENT_DEFAULT)
+            .help("Number of repos for which to concurrently compute status")
+            .short("c")
+            .long("concurrent"),
+        Arg::with_name(FILES_ARG)
+            .help("Lists the paths of conflicted, indexed, modified, and untracked files")
+            .short("f")
+            .long("files"),
+        negation(NO_FILES_ARG, "no-files", FILES_ARG),
+        Arg::with_name(MAX_FILES_ARG)
+            .default_value(MAX_FILES_DEFAULT)
+            .help("Maximum number of paths to list per repo with -f/--files")
+            .long("max-files")
+            .value_name("N"),
+        Arg::with_name(SHORT_ARG)
+            .help("Shows one line per repo with branch, change counts, and ahead/behind")
+            .short("s")
+            .long("short"),
+        negation(NO_SHORT_ARG, "no-short", SHORT_ARG),
+        Arg::with_name(STALE_ARG)
+            .help("Warns about remotes not fetched within DURATION (e.g. 12h, 2w)")
+            .long("stale")
+            .value_name("DURATION"),
         Arg::with_name(TAG_ARG)
             .help("Limits/groups display to repos with specified tag(s)")
             .short("t")
@@ -48,194 +146,1169 @@ pub fn args<'a>() -> Vec<Arg<'a, 'a>> {
             .help("Shows defaults in addition to user-specified config")
             .short("v")
             .long("verbose"),
+        negation(NO_VERBOSE_ARG, "no-verbose", VERBOSE_ARG),
+        Arg::with_name(WATCH_ARG)
+            .help("Shows status full-screen, updating as repos change")
+            .short("w")
+            .long("watch"),
+        negation(NO_WATCH_ARG, "no-watch", WATCH_ARG),
     ]
 }
 
 /// Executes the `status` subcommand.
 pub fn run(invocation: &Invocation) {
-    let verbose = invocation.matches().is_present(VERBOSE_ARG);
-    let header = Style::new().bold().underline();
-    let mut cache: HashMap<&Repo, Summary> = HashMap::new();
-    for (tag, repos) in invocation.iter_tags(TAG_ARG) {
-        if let Some(tag) = tag {
-            println!("\n{}{}", header.paint("TAG:"), header.paint(tag));
-        } else {
-            println!();
-        }
-        for (name, repo) in repos {
-            if cache.get(repo).is_none() {
-                let mut summary = Summary::new();
-                let git = repo.git();
-
-                let mut status_options = StatusOptions::new();
-                status_options.show(StatusShow::IndexAndWorkdir);
-                status_options.exclude_submodules(true);
-                status_options.renames_head_to_index(true);
-                status_options.renames_index_to_workdir(true);
-                status_options.renames_from_rewrites(true);
-                status_options.include_untracked(true);
-                status_options.recurse_untracked_dirs(true);
-
-                if let Ok(statuses) = git.statuses(Some(&mut status_options)) {
-                    /// Returns a new `Note` for the given status result.
-                    fn note_for_status(group: usize, count: usize, description: &str) -> Note {
-                        let kind = if count > 0 { Kind::Failure } else { Kind::None };
-                        let files = if count == 1 { "file is" } else { "files are" };
-                        Note::new(group, kind, &format!("{} {} {}", count, files, description))
+    let verbose = invocation.is_present(VERBOSE_ARG);
+    let concurrent = concurrent(invocation, CONCURRENT_ARG);
+    let max_files = if invocation.is_present(FILES_ARG) {
+        let max_files_str = invocation
+            .value_of(MAX_FILES_ARG)
+            .unwrap_or_else(|| panic!("expected {} to have an argument", MAX_FILES_ARG));
+        match max_files_str.parse::<usize>() {
+            Ok(max_files) => Some(max_files),
+            Err(e) => {
+                return invocation.control().fatal(&format!(
+                    "failed to interpret value '{}' for {} ({})",
+                    max_files_str, MAX_FILES_ARG, e
+                ));
+            }
+        }
+    } else {
+        None
+    };
+    let stale = match invocation.value_of(STALE_ARG) {
+        Some(stale_str) => match parse_duration(stale_str) {
+            Ok(stale) => Some(stale),
+            Err(e) => {
+                return invocation.control().fatal(&format!(
+                    "failed to interpret value '{}' for {} ({})",
+                    stale_str,
+                    STALE_ARG,
+                    e.message()
+                ));
+            }
+        },
+        None => None,
+    };
+
+    // Make a list of the repos we need the status of, taking -t/--tag into account.
+    // The list is deduplicated since the same repo may be yielded for multiple tags.
+    let mut repo_list = Vec::new();
+    for (_, repos) in invocation.iter_tags(TAG_ARG) {
+        for (_, repo) in repos {
+            if !repo_list.contains(&repo) {
+                repo_list.push(repo);
+            }
+        }
+    }
+
+    let short = invocation.is_present(SHORT_ARG);
+    let watching = invocation.is_present(WATCH_ARG);
+    if (short || watching) && !invocation.format().is_text() {
+        return invocation.control().fatal(&format!(
+            "{} requires --format=human or --format=plain",
+            if watching { "--watch" } else { "--short" }
+        ));
+    }
+    if watching {
+        return watch(
+            invocation, &repo_list, concurrent, max_files, stale, short, verbose,
+        );
+    }
+
+    let cache = summarize_all(invocation, &repo_list, concurrent, max_files, stale);
+    if invocation.sigterms_received() > 0 {
+        return invocation.control().interrupted();
+    }
+
+    if !short {
+        return render(
+            invocation,
+            TAG_ARG,
+            Field::Name,
+            emphasis(verbose),
+            |repo| display_summary(lookup(&cache, repo), verbose),
+        );
+    }
+
+    // Short output is truncated to the width of the terminal (if there is one) so
+    // that each repo really does take up a single line.
+    let width = termion::terminal_size().ok().map(|(w, _)| w as usize);
+    let mut text = text(invocation, emphasis(verbose));
+    push_status(&mut text, invocation, &cache, short, verbose);
+    for line in text.into_lines() {
+        match width {
+            Some(width) => println!("{}", paint(&truncate(&line, width, invocation.ascii()))),
+            None => println!("{}", paint(&line)),
+        }
+    }
+    println!();
+}
+
+// ----- watch ----------------------------------------------------------------
+
+/// Shows the status of `repos` full-screen, recomputing the status of a repo when
+/// its worktree or git directory changes, until the user hits `q` or Ctrl-c (or
+/// mgit receives a termination signal).
+///
+/// Repos that can't be watched are polled every `WATCH_POLL_SECONDS` instead.
+///
+/// See `summarize_all()` for the meaning of `concurrent`, `max_files` and `stale`, and
+/// `push_status()` for `short` and `verbose`.
+fn watch(
+    invocation: &Invocation,
+    repos: &[&Repo],
+    concurrent: u8,
+    max_files: Option<usize>,
+    stale: Option<u64>,
+    short: bool,
+    verbose: bool,
+) {
+    let watcher = match Watcher::new() {
+        Ok(watcher) => watcher,
+        Err(e) => return invocation.control().fatal(e.message()),
+    };
+
+    // Maps each `Watch` to the repo and directory it is watching. For each repo we
+    // watch every (non-ignored) directory in the worktree, the git directory itself
+    // (for HEAD, the index, etc), and everything under `refs/`.
+    let mut dirs = HashMap::new();
+    // Maps repos that could not be (fully) watched to the time their status was last
+    // computed. The message for the most recent failure is kept in `watch_error`.
+    let mut polled: HashMap<&Repo, Instant> = HashMap::new();
+    let mut watch_error = None;
+    for repo in repos {
+        let git = repo.git();
+        let mut roots = vec![git.path().join("refs")];
+        if let Some(workdir) = git.workdir() {
+            roots.push(workdir.to_path_buf());
+        }
+        let mut result = watch_dir(&watcher, repo, git.path(), &mut dirs);
+        for root in roots {
+            if result.is_ok() {
+                result = watch_tree(&watcher, repo, &git, &root, &mut dirs);
+            }
+        }
+        if let Err(e) = result {
+            polled.insert(repo, Instant::now());
+            watch_error = Some(e.message().to_owned());
+        }
+    }
+
+    let mut cache = summarize_all(invocation, repos, concurrent, max_files, stale);
+    if invocation.sigterms_received() > 0 {
+        return invocation.control().interrupted();
+    }
+
+    let mut stdout = match stdout().into_raw_mode() {
+        Ok(stdout) => stdout,
+        Err(e) => {
+            return invocation
+                .control()
+                .fatal(&format!("failed to put terminal into raw mode ({})", e));
+        }
+    };
+    let mut stdin = termion::async_stdin().keys();
+    let mut resize = ResizeDebounce::new();
+
+    // Maps repos with changes to the time of the most recent change.
+    let mut pending: HashMap<&Repo, Instant> = HashMap::new();
+    // Indicates whether a summary has changed since the last time the UI was drawn.
+    let mut changed = false;
+
+    let t = Duration::from_millis(1000 / UPDATE_FREQUENCY);
+    let debounce = Duration::from_millis(WATCH_DEBOUNCE_MILLIS);
+    let poll = Duration::from_secs(WATCH_POLL_SECONDS);
+    'main: loop {
+        // Signals don't exit the process for this command (see `EXIT_ON_SIGTERM`), so
+        // the terminal can be reset below.
+        if invocation.sigterms_received() > 0 {
+            break 'main;
+        }
+
+        // Process any keystrokes, looking for q or ctrl-c.
+        for key in &mut stdin {
+            match key.expect("failed to parse keyboard input") {
+                Key::Char('q') | Key::Ctrl('c') => break 'main,
+                _ => {}
+            }
+        }
+
+        // Mark repos with changes as pending, and start watching any new directories.
+        for event in watcher.read() {
+            if event.is_overflow() {
+                for repo in repos {
+                    pending.insert(repo, Instant::now());
+                }
+                continue;
+            }
+            if event.is_removed() {
+                dirs.remove(&event.watch());
+                continue;
+            }
+            let (repo, dir) = match dirs.get(&event.watch()) {
+                Some(&(repo, ref dir)) => (repo, dir.to_path_buf()),
+                None => continue,
+            };
+            if event.is_new_dir() {
+                if let Some(name) = event.name() {
+                    let git = repo.git();
+                    if let Err(e) = watch_tree(&watcher, repo, &git, &dir.join(name), &mut dirs) {
+                        polled.entry(repo).or_insert_with(Instant::now);
+                        watch_error = Some(e.message().to_owned());
+                        changed = true;
                     }
+                }
+            }
+            pending.insert(repo, Instant::now());
+        }
 
-                    let indexed = statuses
-                        .iter()
-                        .filter(|status_entry| {
-                            status_entry.status().intersects(
-                                Status::INDEX_DELETED
-                                    | Status::INDEX_MODIFIED
-                                    | Status::INDEX_NEW
-                                    | Status::INDEX_RENAMED
-                                    | Status::INDEX_TYPECHANGE,
-                            )
-                        })
-                        .count();
-                    summary.push_note(note_for_status(
-                        STATUS_INDEXED_GROUP,
-                        indexed,
-                        "changed in index but uncommitted",
-                    ));
-                    let modified = statuses
-                        .iter()
-                        .filter(|status_entry| {
-                            status_entry.status().intersects(
-                                Status::WT_DELETED
-                                    | Status::WT_MODIFIED
-                                    | Status::WT_RENAMED
-                                    | Status::WT_TYPECHANGE,
-                            )
-                        })
-                        .count();
-                    summary.push_note(note_for_status(STATUS_MODIFIED_GROUP, modified, "modified"));
-                    let untracked = statuses
-                        .iter()
-                        .filter(|status_entry| status_entry.status().intersects(Status::WT_NEW))
-                        .count();
-                    summary.push_note(note_for_status(
-                        STATUS_UNTRACKED_GROUP,
-                        untracked,
-                        "untracked",
-                    ));
+        // Recompute status for repos whose changes have settled.
+        let settled = pending
+            .iter()
+            .filter(|&(_, instant)| instant.elapsed() >= debounce)
+            .map(|(repo, _)| *repo)
+            .collect::<Vec<&Repo>>();
+        for repo in settled {
+            pending.remove(repo);
+            cache.insert(repo, summarize(repo, max_files, stale));
+            changed = true;
+        }
+
+        // Recompute status for repos that are polled, if it is time.
+        for (repo, instant) in &mut polled {
+            if instant.elapsed() >= poll {
+                *instant = Instant::now();
+                cache.insert(repo, summarize(repo, max_files, stale));
+                changed = true;
+            }
+        }
+
+        let size = match resize.check() {
+            Resize::Draw(w, h) => Some((w, h)),
+            Resize::Pending => None,
+            Resize::Unchanged => {
+                if changed {
+                    Some(termion::terminal_size().expect("failed to get terminal size"))
                 } else {
-                    summary.push_note(Note::new(
-                        STATUS_FAILURE_GROUP,
-                        Kind::Failure,
-                        "failed to get status info",
-                    ));
+                    None
                 }
+            }
+        };
+        if let Some((w, h)) = size {
+            let mut text = text(invocation, emphasis(verbose));
+            text.push_line(
+                Style::new().dimmed(),
+                format!(
+                    "watching {} repos for changes (press q to quit)",
+                    repos.len()
+                ),
+            );
+            if let Some(ref message) = watch_error {
+                text.push_line(
+                    invocation.theme().style_for_kind(&Kind::Warning),
+                    format!(
+                        "polling {} repos every {}s instead of watching them ({})",
+                        polled.len(),
+                        WATCH_POLL_SECONDS,
+                        message
+                    ),
+                );
+            }
+            push_status(&mut text, invocation, &cache, short, verbose);
+            draw(&mut stdout, w, h, invocation.ascii(), &text.into_lines());
+            resize.drawn(w, h);
+            changed = false;
+        }
+
+        thread::sleep(t);
+    }
+
+    write!(
+        stdout,
+        "{}{}{}",
+        clear::All,
+        cursor::Goto(1, 1),
+        cursor::Show
+    )
+    .expect("failed to write content to the terminal");
+    stdout
+        .flush()
+        .expect("failed to flush content to the terminal");
+
+    // Exiting skips destructors, so the terminal has to be taken out of raw mode
+    // first.
+    if invocation.sigterms_received() > 0 {
+        drop(stdout);
+        invocation.control().interrupted();
+    }
+}
+
+// ----- watch_dir --------------------------------------------------------------
+
+/// Starts watching the directory at `path` for changes, recording the `Watch` in
+/// `dirs`.
+fn watch_dir<'a>(
+    watcher: &Watcher,
+    repo: &'a Repo,
+    path: &Path,
+    dirs: &mut HashMap<Watch, (&'a Repo, PathBuf)>,
+) -> Result<(), Error> {
+    let watch = watcher.add(path)?;
+    dirs.insert(watch, (repo, path.to_path_buf()));
+    Ok(())
+}
+
+// ----- watch_tree -------------------------------------------------------------
+
+/// Starts watching the directory at `root` and all of its subdirectories for
+/// changes, recording the `Watch`es in `dirs`.
+///
+/// Nested `.git` directories are skipped, as are directories in the worktree that
+/// are ignored by git.
+fn watch_tree<'a>(
+    watcher: &Watcher,
+    repo: &'a Repo,
+    git: &Repository,
+    root: &Path,
+    dirs: &mut HashMap<Watch, (&'a Repo, PathBuf)>,
+) -> Result<(), Error> {
+    let workdir = git.workdir();
+    let is_ignored = |path: &Path| {
+        if path.starts_with(git.path()) {
+            return false;
+        }
+        match workdir.map(|workdir| path.strip_prefix(workdir)) {
+            Some(Ok(relative)) if relative != Path::new("") => {
+                git.is_path_ignored(relative).unwrap_or(false)
+            }
+            _ => false,
+        }
+    };
+    let entries = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        entry.file_type().is_dir() && entry.file_name() != ".git" && !is_ignored(entry.path())
+    });
+    // Errors here are almost always due to directories disappearing out from under
+    // us, which is fine -- there is nothing to watch anymore.
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        watch_dir(watcher, repo, entry.path(), dirs)?;
+    }
+    Ok(())
+}
+
+// ----- draw -------------------------------------------------------------------
+
+/// Clears the terminal `t`, then draws `lines` (truncated to fit the width `w`
+/// and height `h` of the terminal) to it. If `ascii` is `true`, only ASCII
+/// characters are used for the truncation markers.
+#[allow(clippy::cast_possible_truncation)]
+fn draw<W: Write>(t: &mut W, w: u16, h: u16, ascii: bool, lines: &[Line]) {
+    let (w_usize, h_usize) = (w as usize, h as usize);
+    write!(t, "{}", clear::All).expect("failed to write content to the terminal");
+    for (i, line) in lines.iter().enumerate() {
+        let y = i + 1;
+        if lines.len() > h_usize && y == h_usize {
+            // This is the last line available in the terminal, use it to tell the user how
+            // many lines are not displayed (including this one).
+            let mut message = format!("\u{2026}{} more not shown", lines.len() - h_usize + 1);
+            if ascii {
+                message = to_ascii(&message);
+            }
+            let line = truncate(&[(Style::new(), message)], w_usize, ascii);
+            write!(t, "{}{}", cursor::Goto(1, h), paint(&line))
+                .expect("failed to write content to the terminal");
+            break;
+        }
+        write!(
+            t,
+            "{}{}",
+            cursor::Goto(1, y as u16),
+            paint(&truncate(line, w_usize, ascii))
+        )
+        .expect("failed to write content to the terminal");
+    }
+    write!(t, "{}", cursor::Hide).expect("failed to write content to the terminal");
+    t.flush().expect("failed to flush content to the terminal");
+}
+
+// ----- lookup ---------------------------------------------------------------
+
+/// Returns the status of `repo` from `cache`.
+fn lookup<'a>(cache: &'a HashMap<&Repo, RepoStatus>, repo: &Repo) -> &'a RepoStatus {
+    cache.get(repo).unwrap_or_else(|| {
+        panic!(
+            "failed to get summary from cache for repo '{}'",
+            repo.name_or_default()
+        )
+    })
+}
+
+// ----- push_status ----------------------------------------------------------
+
+/// Renders the status of all repos selected by `-t/--tag`, looking it up in
+/// `cache`, into `text`.
+///
+/// If `short` is `true`, each repo gets a single line (see `short_lines()`),
+/// otherwise see `display_summary()` for the meaning of `verbose`.
+fn push_status(
+    text: &mut Text,
+    invocation: &Invocation,
+    cache: &HashMap<&Repo, RepoStatus>,
+    short: bool,
+    verbose: bool,
+) {
+    // Short output is aligned in columns across all groups, so we need to see every
+    // repo before we can produce any of the lines.
+    let short_lines = if short {
+        short_lines(cache, invocation.theme())
+    } else {
+        HashMap::new()
+    };
+
+    each_repo(
+        invocation,
+        TAG_ARG,
+        Field::Name,
+        text,
+        |text, repo, name| match short_lines.get(repo) {
+            Some(&(style, ref line)) => text.push_line(style, line.clone()),
+            None => text.repo(repo, name, &display_summary(lookup(cache, repo), verbose)),
+        },
+    );
+}
+
+// ----- short_lines ----------------------------------------------------------
+
+/// Returns one line of output for each repo in `cache`, as a map of `&Repo` to a
+/// `(<style>, <line>)` tuple.
+///
+/// Each line has the repo's symbol and name, the branch HEAD points to, the number
+/// of conflicted (`!`), indexed (`+`), modified (`~`) and untracked (`?`) files,
+/// and how far HEAD is ahead (`↑`) of and behind (`↓`) its upstream. Columns are
+/// padded so that they line up across all of the repos. Lines are styled
+/// according to `theme`.
+fn short_lines<'a>(
+    cache: &HashMap<&'a Repo, RepoStatus>,
+    theme: &Theme,
+) -> HashMap<&'a Repo, (Style, String)> {
+    let columns = cache
+        .iter()
+        .map(|(&repo, status)| {
+            let branch = status.branch.clone().unwrap_or_default();
+            let mut changes = Vec::new();
+            if let Some(ref counts) = status.counts {
+                for &(glyph, count) in &[
+                    ('!', counts.conflicted),
+                    ('+', counts.indexed),
+                    ('~', counts.modified),
+                    ('?', counts.untracked),
+                ] {
+                    if count > 0 {
+                        changes.push(format!("{}{}", glyph, count));
+                    }
+                }
+            }
+            let mut arrows = Vec::new();
+            if let Some((ahead, behind)) = status.upstream {
+                if ahead > 0 {
+                    arrows.push(format!("\u{2191}{}", ahead));
+                }
+                if behind > 0 {
+                    arrows.push(format!("\u{2193}{}", behind));
+                }
+            }
+            (
+                repo,
+                theme.style_for_kind(&status.summary.kind()),
+                format!("{} {}", repo.symbol_or_default(), repo.name_or_default()),
+                branch,
+                changes.join(" "),
+                arrows.join(" "),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Determine how wide each of the padded columns needs to be.
+    let (mut name_w, mut branch_w, mut changes_w) = (0, 0, 0);
+    for (_, _, name, branch, changes, _) in &columns {
+        name_w = cmp::max(name_w, name.chars().count());
+        branch_w = cmp::max(branch_w, branch.chars().count());
+        changes_w = cmp::max(changes_w, changes.chars().count());
+    }
+
+    columns
+        .iter()
+        .map(
+            |&(repo, style, ref name, ref branch, ref changes, ref arrows)| {
+                let line = format!(
+                    "{:name_w$}  {:branch_w$}  {:changes_w$}  {}",
+                    name,
+                    branch,
+                    changes,
+                    arrows,
+                    name_w = name_w,
+                    branch_w = branch_w,
+                    changes_w = changes_w,
+                );
+                (repo, (style, line.trim_end().to_owned()))
+            },
+        )
+        .collect()
+}
+
+// ----- emphasis -------------------------------------------------------------
+
+/// Returns the `Emphasis` for the status of repos: repos with nothing to report
+/// are styled as successes, and notes and paths are only shown styled or at all if
+/// `verbose` is `true`.
+fn emphasis(verbose: bool) -> Emphasis {
+    Emphasis {
+        notes: verbose,
+        none_as_success: true,
+        paths: verbose,
+    }
+}
+
+// ----- display_summary ------------------------------------------------------
+
+/// Returns the `Summary` to display for `repo`, given its `status`.
+///
+/// Unless `verbose` is `true`, notes of kind `Kind::None` are left out. (The path of
+/// the repo is shown by the renderer, see `emphasis()`.)
+fn display_summary(status: &RepoStatus, verbose: bool) -> Summary {
+    let mut rv = Summary::new();
+    for note in status.summary.iter() {
+        if verbose || *note.kind() != Kind::None {
+            rv.push_note(note.clone());
+        }
+    }
+    rv
+}
+
+// ----- summarize_all --------------------------------------------------------
 
-                match TrackingBranches::for_repository(&git) {
-                    Ok(branches) => {
-                        for branch in branches {
-                            let local_name = branch.local_name();
-                            let upstream_name = branch.upstream_name();
-                            let (ahead, behind) = match git
-                                .graph_ahead_behind(branch.local_oid(), branch.upstream_oid())
-                            {
-                                Ok((ahead, behind)) => (ahead, behind),
-                                Err(e) => {
-                                    summary.push_note(Note::new(
-                                        BRANCH_FAILURE_GROUP,
-                                        Kind::Failure,
-                                        &format!(
-                                            "failed to determine relationship between local \
-                                             branch {} and upstream branch {} ({})",
-                                            local_name, upstream_name, e,
-                                        ),
-                                    ));
-                                    continue;
-                                }
-                            };
-                            if ahead > 0 && behind > 0 {
-                                summary.push_note(Note::new(
-                                    BRANCH_STATUS_GROUP,
-                                    Kind::Failure,
-                                    &format!(
-                                        "{} has diverged from {} ({} and {} commits)",
-                                        local_name, upstream_name, ahead, behind
-                                    ),
-                                ));
-                            } else if ahead > 0 {
-                                let s = if ahead == 1 { "" } else { "s" };
-                                summary.push_note(Note::new(
-                                    BRANCH_STATUS_GROUP,
-                                    Kind::Warning,
-                                    &format!(
-                                        "{} is ahead of {} by {} commit{}",
-                                        local_name, upstream_name, ahead, s
-                                    ),
-                                ));
-                            } else if behind > 0 {
-                                let s = if behind == 1 { "" } else { "s" };
-                                summary.push_note(Note::new(
-                                    BRANCH_STATUS_GROUP,
-                                    Kind::Failure,
-                                    &format!(
-                                        "{} is behind {} by {} commit{}",
-                                        local_name, upstream_name, behind, s
-                                    ),
-                                ));
-                            } else {
-                                summary.push_note(Note::new(
-                                    BRANCH_STATUS_GROUP,
-                                    Kind::None,
-                                    &format!("{} is up to date with {}", local_name, upstream_name),
-                                ));
-                            }
+/// Computes the `RepoStatus` for each of `repos` using a pool of `concurrent`
+/// worker threads, returning a map of `&Repo` to its status. If mgit receives a
+/// termination signal, the workers stop and the map is incomplete.
+///
+/// See `summarize()` for the meaning of `max_files` and `stale`.
+fn summarize_all<'a>(
+    invocation: &Invocation,
+    repos: &[&'a Repo],
+    concurrent: u8,
+    max_files: Option<usize>,
+    stale: Option<u64>,
+) -> HashMap<&'a Repo, RepoStatus> {
+    // Workers pull repos off `repo_rx` until it is empty, sending a `(&Repo,
+    // RepoStatus)` message back over `results_tx` for each.
+    let (repo_tx, repo_rx) = crossbeam_channel::unbounded();
+    for repo in repos {
+        repo_tx
+            .send(*repo)
+            .expect("failed to queue repo for status");
+    }
+    drop(repo_tx);
+    let (results_tx, results_rx) = crossbeam_channel::unbounded();
+    crossbeam::scope(|scope| {
+        for i in 0..concurrent {
+            let repo_rx = repo_rx.clone();
+            let results_tx = results_tx.clone();
+            scope
+                .builder()
+                .name(format!("status:{}", i))
+                .spawn(move |_| {
+                    for repo in repo_rx.iter() {
+                        if invocation.sigterms_received() > 0 {
+                            break;
                         }
+                        results_tx
+                            .send((repo, summarize(repo, max_files, stale)))
+                            .expect("failed to transmit results to main thread");
                     }
-                    Err(errors) => {
-                        for error in errors {
+                })
+                .expect("failed to spawn thread for status");
+        }
+    })
+    .expect("one or more threads panicked");
+    drop(results_tx);
+    results_rx.iter().collect()
+}
+
+// ----- summarize ------------------------------------------------------------
+
+/// Computes and returns the `RepoStatus` for `repo`.
+///
+/// If `max_files` is `Some`, the status notes for conflicted, indexed, modified and
+/// untracked files include (up to `max_files`) paths as details.
+///
+/// If `stale` is `Some`, remotes that were last fetched more than that many seconds
+/// ago are noted as warnings.
+fn summarize(repo: &Repo, max_files: Option<usize>, stale: Option<u64>) -> RepoStatus {
+    let mut summary = Summary::new();
+    let git = repo.git();
+
+    let operation = match git.state() {
+        RepositoryState::Clean => None,
+        RepositoryState::Merge => Some("merge"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("cherry-pick"),
+        RepositoryState::Bisect => Some("bisect"),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => Some("rebase"),
+        RepositoryState::ApplyMailbox => Some("am"),
+        RepositoryState::ApplyMailboxOrRebase => Some("am or rebase"),
+    };
+    if let Some(operation) = operation {
+        summary.push_note(Note::new(
+            STATE_FAILURE_GROUP,
+            Kind::Failure,
+            &format!("{} is in progress", operation),
+        ));
+    }
+
+    match git.head_detached() {
+        Ok(true) => {
+            let message = match git.head().ok().and_then(|head| head.target()) {
+                Some(oid) => format!("HEAD is detached at {:.7}", oid.to_string()),
+                None => "HEAD is detached".to_owned(),
+            };
+            summary.push_note(Note::new(STATE_FAILURE_GROUP, Kind::Failure, &message));
+        }
+        Ok(false) => {}
+        Err(e) => {
+            summary.push_note(Note::new(
+                STATE_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to determine whether HEAD is detached ({})", e),
+            ));
+        }
+    }
+
+    // The branch HEAD points to, or the abbreviated oid of the commit if HEAD is
+    // detached. For a branch with no commits yet, HEAD can't be resolved but still
+    // names the branch.
+    let branch = match git.head() {
+        Ok(head) => match git.head_detached() {
+            Ok(true) => head.target().map(|oid| format!("{:.7}", oid.to_string())),
+            _ => head.shorthand().map(|name| name.to_owned()),
+        },
+        Err(_) => git.find_reference("HEAD").ok().and_then(|head| {
+            head.symbolic_target()
+                .map(|target| target.trim_start_matches("refs/heads/").to_owned())
+        }),
+    };
+
+    let mut counts = None;
+    if let Ok(statuses) = git.statuses(Some(&mut status_options())) {
+        /// Returns a new `Note` for the given status result.
+        fn note_for_status(group: usize, count: usize, description: &str) -> Note {
+            let kind = if count > 0 { Kind::Failure } else { Kind::None };
+            let files = if count == 1 { "file is" } else { "files are" };
+            Note::new(group, kind, &format!("{} {} {}", count, files, description))
+        }
+
+        let status_counts = Counts::new(&statuses);
+        let mut files = match max_files {
+            Some(max_files) => list_files(&statuses, max_files),
+            None => HashMap::new(),
+        };
+        for &(group, count, description) in &[
+            (
+                STATUS_CONFLICTED_GROUP,
+                status_counts.conflicted,
+                "conflicted",
+            ),
+            (
+                STATUS_INDEXED_GROUP,
+                status_counts.indexed,
+                "changed in index but uncommitted",
+            ),
+            (STATUS_MODIFIED_GROUP, status_counts.modified, "modified"),
+            (STATUS_UNTRACKED_GROUP, status_counts.untracked, "untracked"),
+        ] {
+            let details = files.remove(&group).unwrap_or_default();
+            summary.push_note(note_for_status(group, count, description).with_details(details));
+        }
+        counts = Some(status_counts);
+    } else {
+        summary.push_note(Note::new(
+            STATUS_FAILURE_GROUP,
+            Kind::Failure,
+            "failed to get status info",
+        ));
+    }
+
+    push_stash_notes(&mut summary, repo);
+    push_worktree_notes(&mut summary, repo);
+    push_fetch_notes(&mut summary, repo, stale);
+
+    let mut upstream = None;
+    match TrackingBranches::for_repository(&git) {
+        Ok(branches) => {
+            for branch in branches {
+                let local_name = branch.local_name();
+                let upstream_name = branch.upstream_name();
+                let (ahead, behind) =
+                    match git.graph_ahead_behind(branch.local_oid(), branch.upstream_oid()) {
+                        Ok((ahead, behind)) => (ahead, behind),
+                        Err(e) => {
                             summary.push_note(Note::new(
                                 BRANCH_FAILURE_GROUP,
                                 Kind::Failure,
-                                error.message(),
+                                &format!(
+                                    "failed to determine relationship between local \
+                                 branch {} and upstream branch {} ({})",
+                                    local_name, upstream_name, e,
+                                ),
                             ));
+                            continue;
                         }
-                    }
+                    };
+                if branch.local().is_head() {
+                    upstream = Some((ahead, behind));
+                }
+                if ahead > 0 && behind > 0 {
+                    summary.push_note(Note::new(
+                        BRANCH_STATUS_GROUP,
+                        Kind::Failure,
+                        &format!(
+                            "{} has diverged from {} ({} and {} commits)",
+                            local_name, upstream_name, ahead, behind
+                        ),
+                    ));
+                } else if ahead > 0 {
+                    let s = if ahead == 1 { "" } else { "s" };
+                    summary.push_note(Note::new(
+                        BRANCH_STATUS_GROUP,
+                        Kind::Warning,
+                        &format!(
+                            "{} is ahead of {} by {} commit{}",
+                            local_name, upstream_name, ahead, s
+                        ),
+                    ));
+                } else if behind > 0 {
+                    let s = if behind == 1 { "" } else { "s" };
+                    summary.push_note(Note::new(
+                        BRANCH_STATUS_GROUP,
+                        Kind::Failure,
+                        &format!(
+                            "{} is behind {} by {} commit{}",
+                            local_name, upstream_name, behind, s
+                        ),
+                    ));
+                } else {
+                    summary.push_note(Note::new(
+                        BRANCH_STATUS_GROUP,
+                        Kind::None,
+                        &format!("{} is up to date with {}", local_name, upstream_name),
+                    ));
                 }
+            }
+        }
+        Err(errors) => {
+            for error in errors {
+                summary.push_note(Note::new(
+                    BRANCH_FAILURE_GROUP,
+                    Kind::Failure,
+                    error.message(),
+                ));
+            }
+        }
+    }
 
-                cache.insert(repo, summary);
+    RepoStatus {
+        summary,
+        branch,
+        counts,
+        upstream,
+    }
+}
+
+// ----- RepoStatus -----------------------------------------------------------
+
+/// Status of a repo: the `Summary` shown by default, plus the few facts that are
+/// condensed into a single line by `-s/--short`.
+struct RepoStatus {
+    /// Notes describing the status of the repo.
+    summary: Summary,
+    /// Name of the branch HEAD points to (or the abbreviated oid HEAD is detached
+    /// at), if it could be determined.
+    branch: Option<String>,
+    /// Number of files in each "interesting" state, if status could be computed.
+    counts: Option<Counts>,
+    /// Number of commits HEAD's branch is ahead of and behind its upstream, if it
+    /// has one.
+    upstream: Option<(usize, usize)>,
+}
+
+// ----- status_options -------------------------------------------------------
+
+/// Returns the `StatusOptions` used to compute worktree status.
+fn status_options() -> StatusOptions {
+    let mut status_options = StatusOptions::new();
+    status_options.show(StatusShow::IndexAndWorkdir);
+    status_options.exclude_submodules(true);
+    status_options.renames_head_to_index(true);
+    status_options.renames_index_to_workdir(true);
+    status_options.renames_from_rewrites(true);
+    status_options.include_untracked(true);
+    status_options.recurse_untracked_dirs(true);
+    status_options
+}
+
+// ----- Counts ---------------------------------------------------------------
+
+/// Number of files in each of the "interesting" states for a worktree.
+struct Counts {
+    /// Number of files with conflicts.
+    conflicted: usize,
+    /// Number of files changed in the index but uncommitted.
+    indexed: usize,
+    /// Number of modified files.
+    modified: usize,
+    /// Number of untracked files.
+    untracked: usize,
+}
+
+impl Counts {
+    /// Counts the entries in `statuses` and returns a new `Counts` instance.
+    fn new(statuses: &Statuses) -> Self {
+        let count = |flags: Status| {
+            statuses
+                .iter()
+                .filter(|status_entry| status_entry.status().intersects(flags))
+                .count()
+        };
+        Self {
+            conflicted: count(Status::CONFLICTED),
+            indexed: count(
+                Status::INDEX_DELETED
+                    | Status::INDEX_MODIFIED
+                    | Status::INDEX_NEW
+                    | Status::INDEX_RENAMED
+                    | Status::INDEX_TYPECHANGE,
+            ),
+            modified: count(
+                Status::WT_DELETED
+                    | Status::WT_MODIFIED
+                    | Status::WT_RENAMED
+                    | Status::WT_TYPECHANGE,
+            ),
+            untracked: count(Status::WT_NEW),
+        }
+    }
+
+    /// Returns `true` if any of the counts is non-zero.
+    fn is_dirty(&self) -> bool {
+        self.conflicted + self.indexed + self.modified + self.untracked > 0
+    }
+
+    /// Returns a short description of the non-zero counts (e.g. `"2 modified, 1
+    /// untracked"`).
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for &(count, description) in &[
+            (self.conflicted, "conflicted"),
+            (self.indexed, "indexed"),
+            (self.modified, "modified"),
+            (self.untracked, "untracked"),
+        ] {
+            if count > 0 {
+                parts.push(format!("{} {}", count, description));
             }
-            let summary = cache.get(repo).unwrap_or_else(|| {
-                panic!(
-                    "failed to get summary from cache for repo '{}'",
-                    repo.name_or_default()
-                )
-            });
-            let color = match summary.kind() {
-                Kind::None | Kind::Success => Color::Green,
-                Kind::Warning => Color::Yellow,
-                Kind::Failure => Color::Red,
-            };
-            let full_path = if verbose {
-                format!(" \n  \u{2022} {}", repo.full_path())
+        }
+        parts.join(", ")
+    }
+}
+
+// ----- list_files -----------------------------------------------------------
+
+/// Returns a map of status group number to a list of the files in that group,
+/// formatted for display.
+///
+/// Each line is a status letter followed by the path (or `old → new` for renames).
+/// At most `max_files` lines are returned in total; for each group with files that
+/// did not make the cut, a final line notes how many were left out.
+fn list_files(statuses: &Statuses, max_files: usize) -> HashMap<usize, Vec<String>> {
+    /// Returns the path for `delta`, formatted as `old → new` if `renamed`.
+    fn describe(path: &str, delta: Option<DiffDelta>, renamed: bool) -> String {
+        if renamed {
+            if let Some(delta) = delta {
+                if let (Some(old), Some(new)) = (delta.old_file().path(), delta.new_file().path()) {
+                    return format!("{} \u{2192} {}", old.display(), new.display());
+                }
+            }
+        }
+        path.to_owned()
+    }
+
+    let mut rv: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut hidden: HashMap<usize, usize> = HashMap::new();
+    let mut listed = 0;
+    for status_entry in statuses.iter() {
+        let status = status_entry.status();
+        let path = String::from_utf8_lossy(status_entry.path_bytes());
+        let mut lines = Vec::new();
+        if status.intersects(Status::CONFLICTED) {
+            lines.push((STATUS_CONFLICTED_GROUP, format!("U  {}", path)));
+        }
+        let indexed = if status.intersects(Status::INDEX_NEW) {
+            Some("A")
+        } else if status.intersects(Status::INDEX_MODIFIED) {
+            Some("M")
+        } else if status.intersects(Status::INDEX_DELETED) {
+            Some("D")
+        } else if status.intersects(Status::INDEX_RENAMED) {
+            Some("R")
+        } else if status.intersects(Status::INDEX_TYPECHANGE) {
+            Some("T")
+        } else {
+            None
+        };
+        if let Some(letter) = indexed {
+            let renamed = status.intersects(Status::INDEX_RENAMED);
+            let path = describe(&path, status_entry.head_to_index(), renamed);
+            lines.push((STATUS_INDEXED_GROUP, format!("{}  {}", letter, path)));
+        }
+        let modified = if status.intersects(Status::WT_MODIFIED) {
+            Some("M")
+        } else if status.intersects(Status::WT_DELETED) {
+            Some("D")
+        } else if status.intersects(Status::WT_RENAMED) {
+            Some("R")
+        } else if status.intersects(Status::WT_TYPECHANGE) {
+            Some("T")
+        } else {
+            None
+        };
+        if let Some(letter) = modified {
+            let renamed = status.intersects(Status::WT_RENAMED);
+            let path = describe(&path, status_entry.index_to_workdir(), renamed);
+            lines.push((STATUS_MODIFIED_GROUP, format!("{}  {}", letter, path)));
+        }
+        if status.intersects(Status::WT_NEW) {
+            lines.push((STATUS_UNTRACKED_GROUP, format!("?  {}", path)));
+        }
+        for (group, line) in lines {
+            if listed < max_files {
+                rv.entry(group).or_default().push(line);
+                listed += 1;
             } else {
-                "".to_owned()
-            };
-            println!(
-                "{} {}{}",
-                color.bold().paint(repo.symbol_or_default()),
-                color.bold().paint(name),
-                full_path
-            );
-            for note in summary.iter() {
-                let style = match (verbose, note.kind()) {
-                    (true, &Kind::Warning) => Color::Yellow.normal(),
-                    (true, &Kind::Failure) => Color::Red.normal(),
-                    (false, _) | (true, &Kind::None) | (true, &Kind::Success) => Style::new(),
+                *hidden.entry(group).or_insert(0) += 1;
+            }
+        }
+    }
+    for (group, count) in hidden {
+        rv.entry(group)
+            .or_default()
+            .push(format!("\u{2026}{} more not shown", count));
+    }
+    rv
+}
+
+// ----- push_stash_notes -----------------------------------------------------
+
+/// Adds a note to `summary` with the number of stash entries for `repo` and the
+/// age of the oldest entry.
+fn push_stash_notes(summary: &mut Summary, repo: &Repo) {
+    let mut git = repo.git();
+    let mut oids = Vec::new();
+    if let Err(e) = git.stash_foreach(|_, _, oid| {
+        oids.push(*oid);
+        true
+    }) {
+        summary.push_note(Note::new(
+            EXTRA_FAILURE_GROUP,
+            Kind::Failure,
+            &format!("failed to list stash entries ({})", e),
+        ));
+        return;
+    }
+
+    // Stash entries are yielded newest first, so the oldest is the last one.
+    let count = oids.len();
+    let oldest = match oids.last() {
+        Some(oid) => oid,
+        None => {
+            summary.push_note(Note::new(STASH_GROUP, Kind::None, "0 stash entries"));
+            return;
+        }
+    };
+    let entries = if count == 1 { "entry" } else { "entries" };
+    let age = match git.find_commit(*oldest) {
+        Ok(commit) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system time is before the unix epoch")
+                .as_secs() as i64;
+            let seconds = now - commit.time().seconds();
+            let age = format_duration(if seconds > 0 { seconds as u64 } else { 0 });
+            if count == 1 {
+                format!(" ({} old)", age)
+            } else {
+                format!(" (oldest is {} old)", age)
+            }
+        }
+        Err(_) => String::new(),
+    };
+    summary.push_note(Note::new(
+        STASH_GROUP,
+        Kind::Warning,
+        &format!("{} stash {}{}", count, entries, age),
+    ));
+}
+
+// ----- push_fetch_notes -----------------------------------------------------
+
+/// Adds a note to `summary` for each remote of `repo` with the time since it was
+/// last fetched. If `stale` is `Some`, remotes last fetched more than that many
+/// seconds ago are noted as warnings. Remotes that have never been fetched are
+/// only noted, since they are usually ones that were just added (e.g. a
+/// colleague's), not ones that went stale.
+fn push_fetch_notes(summary: &mut Summary, repo: &Repo, stale: Option<u64>) {
+    let git = repo.git();
+    let names = match git.remotes() {
+        Ok(names) => names,
+        Err(e) => {
+            summary.push_note(Note::new(
+                EXTRA_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to list remotes ({})", e),
+            ));
+            return;
+        }
+    };
+    for name in names.iter().flatten() {
+        let note = match last_fetched(&git, name) {
+            Some(time) => {
+                let seconds = SystemTime::now()
+                    .duration_since(time)
+                    .map(|age| age.as_secs())
+                    .unwrap_or(0);
+                let kind = match stale {
+                    Some(stale) if seconds > stale => Kind::Warning,
+                    _ => Kind::None,
                 };
-                if verbose || *note.kind() != Kind::None {
-                    println!("{}", style.paint(format!("  \u{2192} {}", note.message())))
+                Note::new(
+                    FETCH_GROUP,
+                    kind,
+                    &format!("{} last fetched {} ago", name, format_duration(seconds)),
+                )
+            }
+            None => Note::new(
+                FETCH_GROUP,
+                Kind::None,
+                &format!("{} has never been fetched", name),
+            ),
+        };
+        summary.push_note(note);
+    }
+}
+
+// ----- push_worktree_notes --------------------------------------------------
+
+/// Adds a note to `summary` for each linked worktree of `repo`, noting the branch
+/// checked out in the worktree and whether the worktree is dirty.
+///
+/// libgit2 (or at least the version of the bindings mgit uses) does not expose
+/// worktrees, so this asks the git executable via `git worktree list
+/// --porcelain`. Since worktrees are an extra, that is only done for repos that
+/// have linked worktrees (i.e. a `worktrees` directory in the git directory), and
+/// failing to list them is only a warning.
+fn push_worktree_notes(summary: &mut Summary, repo: &Repo) {
+    if !repo.git().path().join("worktrees").is_dir() {
+        return;
+    }
+    let output = match Command::new("git")
+        .args(&["worktree", "list", "--porcelain"])
+        .current_dir(repo.full_path())
+        .output()
+    {
+        Ok(ref output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        Ok(output) => {
+            summary.push_note(Note::new(
+                WORKTREE_GROUP,
+                Kind::Warning,
+                &format!(
+                    "failed to list worktrees ({})",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+            return;
+        }
+        Err(e) => {
+            summary.push_note(Note::new(
+                WORKTREE_GROUP,
+                Kind::Warning,
+                &format!("failed to list worktrees ({})", e),
+            ));
+            return;
+        }
+    };
+
+    // Output is a series of blank-line-separated stanzas, one per worktree. The
+    // first stanza is always the main worktree, which is skipped.
+    for stanza in output.split("\n\n").skip(1) {
+        let mut path = None;
+        let mut branch = None;
+        for line in stanza.lines() {
+            let mut parts = line.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some("worktree"), Some(value)) => path = Some(value),
+                (Some("branch"), Some(value)) => {
+                    branch = Some(value.trim_start_matches("refs/heads/"))
                 }
+                _ => {}
             }
         }
+        let path = match path {
+            Some(path) => path,
+            None => continue,
+        };
+        let description = match branch {
+            Some(branch) => format!("worktree {} (on {})", path, branch),
+            None => format!("worktree {} (detached HEAD)", path),
+        };
+        if !Path::new(path).exists() {
+            summary.push_note(Note::new(
+                WORKTREE_GROUP,
+                Kind::Warning,
+                &format!("{} is missing", description),
+            ));
+            continue;
+        }
+        let note = match Repository::open(path) {
+            Ok(git) => match git.statuses(Some(&mut status_options())) {
+                Ok(statuses) => {
+                    let counts = Counts::new(&statuses);
+                    if counts.is_dirty() {
+                        Note::new(
+                            WORKTREE_GROUP,
+                            Kind::Failure,
+                            &format!("{} is dirty ({})", description, counts.describe()),
+                        )
+                    } else {
+                        Note::new(
+                            WORKTREE_GROUP,
+                            Kind::None,
+                            &format!("{} is clean", description),
+                        )
+                    }
+                }
+                Err(e) => Note::new(
+                    EXTRA_FAILURE_GROUP,
+                    Kind::Failure,
+                    &format!("failed to get status info for {} ({})", description, e),
+                ),
+            },
+            Err(e) => Note::new(
+                EXTRA_FAILURE_GROUP,
+                Kind::Failure,
+                &format!("failed to open {} ({})", description, e),
+            ),
+        };
+        summary.push_note(note);
     }
-    println!();
 }
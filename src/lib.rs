@@ -39,7 +39,10 @@ extern crate walkdir;
 
 mod app;
 mod cmd;
+mod render;
+mod theme;
 mod ui;
+mod watch;
 
 use std::{
     process,
@@ -53,13 +56,22 @@ use std::{
 use signal_hook::{iterator::Signals, SIGINT, SIGTERM};
 
 use app::{init, Command};
-use cmd::{config, pull, status};
+use cmd::{clone, config, pull, remotes, status};
 
-static COMMANDS: [Command; 3] = [
+static COMMANDS: [Command; 5] = [
+    Command {
+        name: clone::NAME,
+        about: clone::ABOUT,
+        exit_on_sigterm: clone::EXIT_ON_SIGTERM,
+        warn_uncloned: clone::WARN_UNCLONED,
+        args: clone::args,
+        run: clone::run,
+    },
     Command {
         name: config::NAME,
         about: config::ABOUT,
         exit_on_sigterm: config::EXIT_ON_SIGTERM,
+        warn_uncloned: config::WARN_UNCLONED,
         args: config::args,
         run: config::run,
     },
@@ -67,13 +79,23 @@ static COMMANDS: [Command; 3] = [
         name: pull::NAME,
         about: pull::ABOUT,
         exit_on_sigterm: pull::EXIT_ON_SIGTERM,
+        warn_uncloned: pull::WARN_UNCLONED,
         args: pull::args,
         run: pull::run,
     },
+    Command {
+        name: remotes::NAME,
+        about: remotes::ABOUT,
+        exit_on_sigterm: remotes::EXIT_ON_SIGTERM,
+        warn_uncloned: remotes::WARN_UNCLONED,
+        args: remotes::args,
+        run: remotes::run,
+    },
     Command {
         name: status::NAME,
         about: status::ABOUT,
         exit_on_sigterm: status::EXIT_ON_SIGTERM,
+        warn_uncloned: status::WARN_UNCLONED,
         args: status::args,
         run: status::run,
     },
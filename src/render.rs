@@ -0,0 +1,725 @@
+//! Output renderers.
+//!
+//! Subcommands describe their results as a `Summary` for each repo, then hand the
+//! summaries to a `Renderer`, which takes care of formatting them. The format is
+//! selected by the user with the top-level `--format` argument.
+use ansi_term::{Color, Style};
+
+use app::{Field, Invocation, Repo};
+use theme::Theme;
+use ui::{ellipsis, json_string, to_ascii, to_ascii_glyph, Kind, Note, Summary};
+
+/// Names of the formats, as accepted by `--format`.
+pub const FORMATS: [&str; 5] = ["human", "plain", "json", "markdown", "html"];
+
+// ----- Format ---------------------------------------------------------------
+
+/// Output format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Text with colors, for people looking at a terminal.
+    Human,
+    /// Text without colors.
+    Plain,
+    /// A single JSON document.
+    Json,
+    /// Markdown document.
+    Markdown,
+    /// HTML document.
+    Html,
+}
+
+impl Format {
+    /// Returns the `Format` named `name` (one of `FORMATS`), or `None` if there is
+    /// no format by that name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(Format::Human),
+            "plain" => Some(Format::Plain),
+            "json" => Some(Format::Json),
+            "markdown" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the format is line-oriented text meant for the terminal
+    /// (`human` or `plain`).
+    pub fn is_text(self) -> bool {
+        self == Format::Human || self == Format::Plain
+    }
+
+    /// Returns a new `Renderer` for the format, set up for `invocation` (see
+    /// `text()`).
+    pub fn renderer(self, invocation: &Invocation, emphasis: Emphasis) -> Box<dyn Renderer> {
+        match self {
+            Format::Human | Format::Plain => Box::new(text(invocation, emphasis)),
+            Format::Json => Box::new(Json::new()),
+            Format::Markdown => Box::new(Markdown::new()),
+            Format::Html => Box::new(Html::new()),
+        }
+    }
+}
+
+// ----- Renderer -------------------------------------------------------------
+
+/// Formats the summaries for a list of repos, grouped by tag.
+pub trait Renderer {
+    /// Starts a new group of repos, for repos with tag `tag` (or all repos, if
+    /// `None`).
+    fn tag(&mut self, tag: Option<&str>);
+
+    /// Adds `repo`, displayed as `name`, and the notes in its `summary` to the
+    /// current group.
+    fn repo(&mut self, repo: &Repo, name: &str, summary: &Summary);
+
+    /// Adds `repo`, displayed as `name`, and its settings (the keyed notes in
+    /// `summary`) to the current group. By default, this is the same as `repo()`.
+    fn settings(&mut self, repo: &Repo, name: &str, summary: &Summary) {
+        self.repo(repo, name, summary)
+    }
+
+    /// Adds a section titled `title` with the notes in `summary`, for information
+    /// that is not about any one repo. Sections should be added before any groups.
+    fn section(&mut self, title: &str, summary: &Summary);
+
+    /// Returns the rendered output.
+    fn finish(&mut self) -> String;
+}
+
+// ----- Emphasis -------------------------------------------------------------
+
+/// Which parts of the results are styled by the `Text` renderer. Commands differ
+/// in what they draw attention to.
+#[derive(Clone, Copy)]
+pub struct Emphasis {
+    /// Indicates whether notes are styled by their kind (otherwise they are plain).
+    pub notes: bool,
+    /// Indicates whether repos with nothing to report (a summary of kind
+    /// `Kind::None`) are styled as successes (otherwise they are plain).
+    pub none_as_success: bool,
+    /// Indicates whether the full path of each repo is shown below its name.
+    pub paths: bool,
+}
+
+impl Emphasis {
+    /// Emphasis for the results of operations, like `pull`: notes are styled, repos
+    /// with nothing to report are not.
+    pub const RESULTS: Emphasis = Emphasis {
+        notes: true,
+        none_as_success: false,
+        paths: false,
+    };
+}
+
+// ----- each_repo ------------------------------------------------------------
+
+/// Starts a group on `renderer` for each tag given to the `-t/--tag` argument
+/// named `arg` (see `Invocation.iter_tags()`), calling `f` with the renderer and
+/// each repo in the group.
+///
+/// Repos are sorted by `field`, which is also what `f` gets as the name of the
+/// repo.
+pub fn each_repo<'a, R, F>(
+    invocation: &'a Invocation,
+    arg: &str,
+    field: Field,
+    renderer: &mut R,
+    mut f: F,
+) where
+    R: Renderer + ?Sized,
+    F: FnMut(&mut R, &'a Repo, &'a str),
+{
+    for (tag, repos) in invocation.iter_tags(arg) {
+        renderer.tag(tag);
+        for (name, repo) in repos.iter_field(field).sorted_by(field) {
+            f(renderer, repo, name);
+        }
+    }
+}
+
+// ----- render ---------------------------------------------------------------
+
+/// Renders the repos selected by the `-t/--tag` argument named `arg` in the format
+/// chosen by the user (with `emphasis`, see `Emphasis`), and prints the result to
+/// stdout.
+///
+/// `summary` is called to get the `Summary` for each repo. See `each_repo()` for
+/// the meaning of `field`.
+pub fn render<'a, F>(
+    invocation: &'a Invocation,
+    arg: &str,
+    field: Field,
+    emphasis: Emphasis,
+    mut summary: F,
+) where
+    F: FnMut(&'a Repo) -> Summary,
+{
+    let mut renderer = invocation.format().renderer(invocation, emphasis);
+    each_repo(
+        invocation,
+        arg,
+        field,
+        &mut *renderer,
+        |renderer, repo, name| renderer.repo(repo, name, &summary(repo)),
+    );
+    print!("{}", renderer.finish());
+}
+
+// ----- text -----------------------------------------------------------------
+
+/// Returns a new `Text` renderer for the invocation, with colors if the format is
+/// `human` and colors are turned on (see `--color`), the theme from the
+/// configuration, and `emphasis`.
+pub fn text(invocation: &Invocation, emphasis: Emphasis) -> Text {
+    Text::new(
+        invocation.format() == Format::Human && invocation.color(),
+        invocation.ascii(),
+        invocation.theme(),
+        emphasis,
+    )
+}
+
+// ----- Line -----------------------------------------------------------------
+
+/// Line of terminal output, as a vec of `(<style>, <text>)` segments.
+pub type Line = Vec<(Style, String)>;
+
+// ----- truncate -------------------------------------------------------------
+
+/// Truncates `line` to `width` characters, ending in an ellipsis (see
+/// `ui::ellipsis()` for the meaning of `ascii`) if truncated.
+pub fn truncate(line: &[(Style, String)], width: usize, ascii: bool) -> Line {
+    let len = line.iter().map(|(_, s)| s.chars().count()).sum::<usize>();
+    if len <= width {
+        return line.to_vec();
+    }
+    let mut rv = Vec::new();
+    // The ASCII marker is wider than one column, so it may itself need cutting down
+    // on a very narrow terminal.
+    let marker = ellipsis(ascii).chars().take(width).collect::<String>();
+    let mut remaining = width - marker.chars().count();
+    for &(style, ref s) in line {
+        if remaining == 0 {
+            break;
+        }
+        let segment = s.chars().take(remaining).collect::<String>();
+        remaining -= segment.chars().count();
+        rv.push((style, segment));
+    }
+    rv.push((Style::new(), marker));
+    rv
+}
+
+// ----- paint ----------------------------------------------------------------
+
+/// Returns `line` as a string, with each segment painted in its style.
+pub fn paint(line: &[(Style, String)]) -> String {
+    line.iter()
+        .map(|&(style, ref s)| style.paint(s.as_str()).to_string())
+        .collect()
+}
+
+// ----- Text -----------------------------------------------------------------
+
+/// Renders line-oriented text for the terminal, with or without colors (the
+/// `human` and `plain` formats).
+///
+/// ```text
+///
+/// <section title>
+///   ┖─ <key>: <value>
+///
+/// TAG:<tag>
+/// <symbol> <name>
+///   • <path>
+///   ┠──── <key>: <value>
+///   ┖─ <longkey>: <value>
+///   → <message>
+///     <detail>
+///
+/// ```
+///
+/// The arrow in front of a message is replaced by the theme's glyph for the kind
+/// of the note, if it has one.
+pub struct Text {
+    /// Indicates whether to use colors.
+    color: bool,
+    /// Indicates whether to use only ASCII characters.
+    ascii: bool,
+    /// Theme for styles and glyphs.
+    theme: Theme,
+    /// Which parts of the results are styled.
+    emphasis: Emphasis,
+    /// Lines rendered so far.
+    lines: Vec<Line>,
+}
+
+impl Text {
+    /// Creates and returns a new `Text` instance, using `theme` for the parts of
+    /// the results chosen by `emphasis`. If `color` is `false`, all styles are
+    /// dropped. If `ascii` is `true`, the text is passed through `ui::to_ascii()`.
+    pub fn new(color: bool, ascii: bool, theme: &Theme, emphasis: Emphasis) -> Self {
+        Self {
+            color,
+            ascii,
+            theme: theme.clone(),
+            emphasis,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Adds a line consisting of `text` in `style`.
+    pub fn push_line(&mut self, style: Style, text: String) {
+        self.push_segments(vec![(style, text)]);
+    }
+
+    /// Returns the lines rendered so far.
+    pub fn into_lines(self) -> Vec<Line> {
+        self.lines
+    }
+
+    /// Adds a line consisting of `segments`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn push_segments(&mut self, segments: Line) {
+        let (color, ascii) = (self.color, self.ascii);
+        let line = segments
+            .into_iter()
+            .map(|(style, s)| {
+                let style = if color { style } else { Style::new() };
+                let s = if ascii { to_ascii(&s) } else { s };
+                (style, s)
+            })
+            .collect();
+        self.lines.push(line);
+    }
+
+    /// Adds the lines for the notes in `summary`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn push_notes(&mut self, summary: &Summary) {
+        // Keyed notes are drawn as a "tree" with the keys right-aligned, so we need the
+        // length of the longest key, and which note is the last one (which gets ┖
+        // instead of ┠).
+        let notes = summary.iter().collect::<Vec<&Note>>();
+        let key_w = notes
+            .iter()
+            .filter_map(|note| note.key())
+            .map(|key| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        let last_keyed = notes.iter().rposition(|note| note.key().is_some());
+
+        for (i, note) in notes.iter().enumerate() {
+            let style = if self.emphasis.notes {
+                self.theme.style_for_kind(note.kind())
+            } else {
+                Style::new()
+            };
+            if let Some(key) = note.key() {
+                // 2500 is "─" (light horizontal box drawing character)
+                let mut h = String::from("\u{2500}");
+                for _ in key.chars().count()..key_w {
+                    h.push('\u{2500}');
+                }
+                // 2516 is "┖" (up heavy and right light)
+                // 2520 is "┠" (vertical heavy and right light)
+                let v = if Some(i) == last_keyed {
+                    "\u{2516}"
+                } else {
+                    "\u{2520}"
+                };
+                self.push_segments(vec![
+                    (Color::Blue.normal(), format!("  {}{} {}: ", v, h, key)),
+                    (style, note.message().to_owned()),
+                ]);
+            } else {
+                let mut glyph = self
+                    .theme
+                    .glyph_for_kind(note.kind())
+                    .unwrap_or("\u{2192}")
+                    .to_owned();
+                if self.ascii {
+                    glyph = to_ascii_glyph(&glyph);
+                }
+                self.push_line(style, format!("  {} {}", glyph, note.message()));
+            }
+            for detail in note.details() {
+                self.push_line(style, format!("    {}", detail));
+            }
+        }
+    }
+}
+
+impl Renderer for Text {
+    fn tag(&mut self, tag: Option<&str>) {
+        self.push_line(Style::new(), String::new());
+        if let Some(tag) = tag {
+            let style = Style::new().bold().underline();
+            self.push_segments(vec![(style, String::from("TAG:")), (style, tag.to_owned())]);
+        }
+    }
+
+    fn repo(&mut self, repo: &Repo, name: &str, summary: &Summary) {
+        let kind = match summary.kind() {
+            Kind::None if self.emphasis.none_as_success => Kind::Success,
+            kind => kind,
+        };
+        let style = self.theme.style_for_kind(&kind).bold();
+        let mut segments = vec![
+            (style, repo.symbol_or_default().to_owned()),
+            (Style::new(), String::from(" ")),
+            (style, name.to_owned()),
+        ];
+        if self.emphasis.paths {
+            // The trailing space is how mgit has always drawn this line.
+            segments.push((Style::new(), String::from(" ")));
+            self.push_segments(segments);
+            // 2022 is "•" (bullet)
+            self.push_line(Style::new(), format!("  \u{2022} {}", repo.full_path()));
+        } else {
+            self.push_segments(segments);
+        }
+        self.push_notes(summary);
+    }
+
+    fn settings(&mut self, _: &Repo, name: &str, summary: &Summary) {
+        self.push_line(Color::Purple.bold(), name.to_owned());
+        self.push_notes(summary);
+    }
+
+    fn section(&mut self, title: &str, summary: &Summary) {
+        self.push_line(Style::new(), String::new());
+        self.push_line(Style::new().bold().underline(), title.to_owned());
+        self.push_notes(summary);
+    }
+
+    fn finish(&mut self) -> String {
+        let mut rv = String::new();
+        for line in &self.lines {
+            rv.push_str(&paint(line));
+            rv.push('\n');
+        }
+        rv.push('\n');
+        rv
+    }
+}
+
+// ----- Json -----------------------------------------------------------------
+
+/// Renders a single JSON document (the `json` format):
+///
+/// ```text
+/// {"groups": [{"tag": <tag or null>, "repos": [{
+///     "name": ..., "symbol": ..., "path": ..., "kind": ...,
+///     "notes": [{"kind": ..., "key": <key or null>, "message": ..., "details": [...]}]
+/// }]}]}
+/// ```
+///
+/// If any sections were added, there is also a `"sections"` key, holding a list of
+/// `{"title": ..., "notes": [...]}`.
+pub struct Json {
+    /// Rendered sections.
+    sections: Vec<String>,
+    /// Rendered groups.
+    groups: Vec<String>,
+    /// Tag (rendered as JSON) for the current group, or `None` if no group has been
+    /// started.
+    tag: Option<String>,
+    /// Rendered repos in the current group.
+    repos: Vec<String>,
+}
+
+impl Json {
+    /// Creates and returns a new `Json` instance.
+    pub fn new() -> Self {
+        Self {
+            sections: Vec::new(),
+            groups: Vec::new(),
+            tag: None,
+            repos: Vec::new(),
+        }
+    }
+
+    /// Adds the current group (if any) to `groups`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn end_group(&mut self) {
+        if let Some(tag) = self.tag.take() {
+            self.groups.push(format!(
+                "{{\"tag\":{},\"repos\":[{}]}}",
+                tag,
+                self.repos.join(",")
+            ));
+            self.repos.clear();
+        }
+    }
+}
+
+impl Renderer for Json {
+    fn tag(&mut self, tag: Option<&str>) {
+        self.end_group();
+        self.tag = Some(match tag {
+            Some(tag) => json_string(tag),
+            None => String::from("null"),
+        });
+    }
+
+    fn repo(&mut self, repo: &Repo, name: &str, summary: &Summary) {
+        self.repos.push(format!(
+            "{{\"name\":{},\"symbol\":{},\"path\":{},\"kind\":{},\"notes\":[{}]}}",
+            json_string(name),
+            json_string(repo.symbol_or_default()),
+            json_string(repo.full_path()),
+            json_string(summary.kind().name()),
+            json_notes(summary)
+        ));
+    }
+
+    fn section(&mut self, title: &str, summary: &Summary) {
+        self.sections.push(format!(
+            "{{\"title\":{},\"notes\":[{}]}}",
+            json_string(title),
+            json_notes(summary)
+        ));
+    }
+
+    fn finish(&mut self) -> String {
+        self.end_group();
+        if self.sections.is_empty() {
+            format!("{{\"groups\":[{}]}}\n", self.groups.join(","))
+        } else {
+            format!(
+                "{{\"sections\":[{}],\"groups\":[{}]}}\n",
+                self.sections.join(","),
+                self.groups.join(",")
+            )
+        }
+    }
+}
+
+// ----- json_notes -----------------------------------------------------------
+
+/// Returns the notes in `summary` as (the inside of) a JSON list.
+fn json_notes(summary: &Summary) -> String {
+    summary
+        .iter()
+        .map(|note| {
+            let details = note
+                .details()
+                .iter()
+                .map(|detail| json_string(detail))
+                .collect::<Vec<String>>();
+            format!(
+                "{{\"kind\":{},\"key\":{},\"message\":{},\"details\":[{}]}}",
+                json_string(note.kind().name()),
+                note.key().map_or(String::from("null"), json_string),
+                json_string(note.message()),
+                details.join(",")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// ----- Markdown -------------------------------------------------------------
+
+/// Renders a Markdown document (the `markdown` format), with a heading for each
+/// section, tag and repo, and a list of the notes for each section and repo.
+pub struct Markdown {
+    /// Document rendered so far.
+    out: String,
+}
+
+impl Markdown {
+    /// Creates and returns a new `Markdown` instance.
+    pub fn new() -> Self {
+        Self { out: String::new() }
+    }
+
+    /// Adds a list of the notes in `summary`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn push_notes(&mut self, summary: &Summary) {
+        let mut empty = true;
+        for note in summary.iter() {
+            let message = markdown_escape(note.message());
+            let item = match (note.key(), note.kind()) {
+                (Some(key), _) => format!("**{}:** {}", markdown_escape(key), message),
+                (None, &Kind::Warning) | (None, &Kind::Failure) => {
+                    format!("**{}:** {}", note.kind().name(), message)
+                }
+                (None, &Kind::None) | (None, &Kind::Success) => message,
+            };
+            self.out.push_str(&format!("- {}\n", item));
+            for detail in note.details() {
+                self.out
+                    .push_str(&format!("  - {}\n", markdown_escape(detail)));
+            }
+            empty = false;
+        }
+        if !empty {
+            self.out.push('\n');
+        }
+    }
+}
+
+impl Renderer for Markdown {
+    fn tag(&mut self, tag: Option<&str>) {
+        if let Some(tag) = tag {
+            self.out
+                .push_str(&format!("## TAG:{}\n\n", markdown_escape(tag)));
+        }
+    }
+
+    fn repo(&mut self, repo: &Repo, name: &str, summary: &Summary) {
+        self.out.push_str(&format!(
+            "### {} {}\n\n",
+            markdown_escape(repo.symbol_or_default()),
+            markdown_escape(name)
+        ));
+        self.push_notes(summary);
+    }
+
+    fn section(&mut self, title: &str, summary: &Summary) {
+        self.out
+            .push_str(&format!("## {}\n\n", markdown_escape(title)));
+        self.push_notes(summary);
+    }
+
+    fn finish(&mut self) -> String {
+        self.out.clone()
+    }
+}
+
+// ----- Html -----------------------------------------------------------------
+
+/// Renders a standalone HTML document (the `html` format), with a heading for
+/// each tag and a `section` for each repo (and each section added with
+/// `Renderer.section()`). Notes are colored according to their kind.
+pub struct Html {
+    /// Body of the document rendered so far.
+    body: String,
+}
+
+impl Html {
+    /// Creates and returns a new `Html` instance.
+    pub fn new() -> Self {
+        Self {
+            body: String::new(),
+        }
+    }
+
+    /// Adds a list of the notes in `summary`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn push_notes(&mut self, summary: &Summary) {
+        let notes = summary.iter().collect::<Vec<&Note>>();
+        if !notes.is_empty() {
+            self.body.push_str("<ul>\n");
+            for note in notes {
+                let message = match note.key() {
+                    Some(key) => format!(
+                        "<strong>{}:</strong> {}",
+                        html_escape(key),
+                        html_escape(note.message())
+                    ),
+                    None => html_escape(note.message()),
+                };
+                self.body
+                    .push_str(&format!("<li class=\"{}\">{}", note.kind().name(), message));
+                if !note.details().is_empty() {
+                    self.body.push_str("<ul>");
+                    for detail in note.details() {
+                        self.body
+                            .push_str(&format!("<li><code>{}</code></li>", html_escape(detail)));
+                    }
+                    self.body.push_str("</ul>");
+                }
+                self.body.push_str("</li>\n");
+            }
+            self.body.push_str("</ul>\n");
+        }
+    }
+}
+
+impl Renderer for Html {
+    fn tag(&mut self, tag: Option<&str>) {
+        if let Some(tag) = tag {
+            self.body
+                .push_str(&format!("<h2>TAG:{}</h2>\n", html_escape(tag)));
+        }
+    }
+
+    fn repo(&mut self, repo: &Repo, name: &str, summary: &Summary) {
+        self.body.push_str(&format!(
+            "<section class=\"repo {}\">\n<h3>{} {}</h3>\n",
+            summary.kind().name(),
+            html_escape(repo.symbol_or_default()),
+            html_escape(name)
+        ));
+        self.push_notes(summary);
+        self.body.push_str("</section>\n");
+    }
+
+    fn section(&mut self, title: &str, summary: &Summary) {
+        self.body
+            .push_str(&format!("<section>\n<h2>{}</h2>\n", html_escape(title)));
+        self.push_notes(summary);
+        self.body.push_str("</section>\n");
+    }
+
+    fn finish(&mut self) -> String {
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>mgit</title>\n\
+             <style>\n\
+             .success {{ color: #2e7d32; }}\n\
+             .warning {{ color: #b26a00; }}\n\
+             .failure {{ color: #c62828; }}\n\
+             </style>\n\
+             </head>\n\
+             <body>\n\
+             {}\
+             </body>\n\
+             </html>\n",
+            self.body
+        )
+    }
+}
+
+// ----- html_escape ----------------------------------------------------------
+
+/// Returns `s` with the characters that are special in HTML escaped.
+fn html_escape(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => rv.push_str("&amp;"),
+            '<' => rv.push_str("&lt;"),
+            '>' => rv.push_str("&gt;"),
+            '"' => rv.push_str("&quot;"),
+            '\'' => rv.push_str("&#39;"),
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+// ----- markdown_escape ------------------------------------------------------
+
+/// Returns `s` with the characters that are special in (inline) Markdown escaped.
+fn markdown_escape(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            rv.push('\\');
+        }
+        rv.push(c);
+    }
+    rv
+}
@@ -0,0 +1,298 @@
+//! Styles and glyphs used to display results.
+//!
+//! The theme is configured in the `[mgit.theme]` section of the configuration.
+//! Keys are `kind.<kind>` (for the kinds of notes, see `ui::Kind`) or
+//! `state.<state>` (for the states of remotes in the `pull` UI), optionally
+//! followed by `.glyph`:
+//!
+//! ```ini
+//! [mgit.theme]
+//! preset = colorblind
+//! kind.failure = bold 161
+//! kind.failure.glyph = ✗
+//! state.fetching = #56b4e9
+//! ```
+//!
+//! Styles are whitespace-separated lists of colors (names, `0`-`255` for the
+//! 256-color palette, or `#rrggbb`) and modifiers (`bold`, `dimmed`, `italic`,
+//! `underline`, `reverse`). A color following `on` sets the background.
+//!
+//! Glyphs for kinds replace the arrow in front of notes. Glyphs for states are
+//! drawn in front of remote names and must be a single character.
+use std::collections::HashMap;
+
+use ansi_term::{Color, Style};
+
+use app::Error;
+use ui::Kind;
+
+/// Names of the built-in themes, as accepted by the `preset` key.
+pub const PRESETS: [&str; 2] = ["default", "colorblind"];
+
+/// Names of the kinds, as used in `kind.<kind>` keys.
+const KINDS: [&str; 4] = ["none", "success", "warning", "failure"];
+
+/// Names of the states of remotes in the `pull` UI, as used in `state.<state>`
+/// keys.
+pub const STATES: [&str; 8] = [
+    "pending",
+    "canceled",
+    "skipped",
+    "fetching",
+    "no-change",
+    "success",
+    "warning",
+    "failure",
+];
+
+// ----- Theme ----------------------------------------------------------------
+
+/// Styles and glyphs for kinds and states.
+#[derive(Clone)]
+pub struct Theme {
+    /// Maps `kind.<kind>` and `state.<state>` keys to their styles. Missing keys
+    /// get the default (plain) style.
+    styles: HashMap<String, Style>,
+    /// Maps `kind.<kind>` and `state.<state>` keys to their glyphs.
+    glyphs: HashMap<String, String>,
+    /// Settings other than `preset` applied so far, in order, so that they can be
+    /// applied again on top of a preset (see `set()`).
+    overrides: Vec<(String, String)>,
+}
+
+impl Theme {
+    /// Creates and returns a new `Theme` instance, using the `default` preset.
+    pub fn new() -> Self {
+        Self::preset("default").expect("failed to get default theme")
+    }
+
+    /// Returns the built-in theme named `name` (one of `PRESETS`), or `None` if
+    /// there is no theme by that name.
+    pub fn preset(name: &str) -> Option<Self> {
+        let (styles, glyphs) = match name {
+            "default" => (
+                vec![
+                    ("kind.success", Color::Green.normal()),
+                    ("kind.warning", Color::Yellow.normal()),
+                    ("kind.failure", Color::Red.normal()),
+                    ("state.pending", Color::Blue.normal()),
+                    ("state.canceled", Style::new().dimmed()),
+                    ("state.skipped", Style::new().dimmed()),
+                    ("state.fetching", Color::Cyan.normal()),
+                    ("state.success", Color::Green.normal()),
+                    ("state.warning", Color::Yellow.normal()),
+                    ("state.failure", Color::Red.normal()),
+                ],
+                vec![],
+            ),
+            // Based on the Okabe-Ito palette: blue, orange and vermillion are
+            // distinguishable with the common forms of color blindness. Glyphs are added
+            // so that results can be told apart without relying on color at all.
+            "colorblind" => (
+                vec![
+                    ("kind.success", Color::Fixed(32).normal()),
+                    ("kind.warning", Color::Fixed(214).normal()),
+                    ("kind.failure", Color::Fixed(166).bold()),
+                    ("state.pending", Style::new().dimmed()),
+                    ("state.canceled", Style::new().dimmed()),
+                    ("state.skipped", Style::new().dimmed()),
+                    ("state.fetching", Color::Fixed(117).normal()),
+                    ("state.success", Color::Fixed(32).normal()),
+                    ("state.warning", Color::Fixed(214).normal()),
+                    ("state.failure", Color::Fixed(166).bold()),
+                ],
+                vec![
+                    ("kind.success", "\u{2713}"),
+                    ("kind.warning", "!"),
+                    ("kind.failure", "\u{2717}"),
+                    ("state.fetching", "\u{2026}"),
+                    ("state.success", "\u{2713}"),
+                    ("state.warning", "!"),
+                    ("state.failure", "\u{2717}"),
+                ],
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            styles: styles
+                .into_iter()
+                .map(|(key, style)| (key.to_owned(), style))
+                .collect(),
+            glyphs: glyphs
+                .into_iter()
+                .map(|(key, glyph)| (key.to_owned(), glyph.to_owned()))
+                .collect(),
+            overrides: Vec::new(),
+        })
+    }
+
+    /// Applies the setting `key` = `value` from the `[mgit.theme]` section.
+    ///
+    /// `preset` only replaces the base of the theme: the other keys applied so far
+    /// (e.g. from earlier config files) are applied again on top of the preset.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        if key == "preset" {
+            return match Self::preset(value) {
+                Some(mut theme) => {
+                    // The overrides were checked when they were first applied, so
+                    // applying them again can't fail.
+                    for (key, value) in &self.overrides {
+                        let _ = theme.apply(key, value);
+                    }
+                    theme.overrides = self.overrides.clone();
+                    *self = theme;
+                    Ok(())
+                }
+                None => Err(Error::new(&format!(
+                    "unknown preset '{}' (expected one of: {})",
+                    value,
+                    PRESETS.join(", ")
+                ))),
+            };
+        }
+        self.apply(key, value)?;
+        self.overrides.push((key.to_owned(), value.to_owned()));
+        Ok(())
+    }
+
+    /// Applies the setting `key` = `value`, where `key` is not `preset`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let (name, is_glyph) = match key.rfind('.') {
+            Some(i) if &key[i + 1..] == "glyph" => (&key[..i], true),
+            _ => (key, false),
+        };
+        let is_known = match name.find('.') {
+            Some(i) => match &name[..i] {
+                "kind" => KINDS.contains(&&name[i + 1..]),
+                "state" => STATES.contains(&&name[i + 1..]),
+                _ => false,
+            },
+            None => false,
+        };
+        if !is_known {
+            return Err(Error::new(&format!("unknown theme key '{}'", key)));
+        }
+
+        if is_glyph {
+            if name.starts_with("state.") && value.chars().count() > 1 {
+                return Err(Error::new(&format!(
+                    "glyph for {} must be a single character (got '{}')",
+                    name, value
+                )));
+            }
+            if value.is_empty() {
+                self.glyphs.remove(name);
+            } else {
+                self.glyphs.insert(name.to_owned(), value.to_owned());
+            }
+        } else {
+            self.styles.insert(name.to_owned(), parse_style(value)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the style for notes (and summaries) of `kind`.
+    pub fn style_for_kind(&self, kind: &Kind) -> Style {
+        self.style(&format!("kind.{}", kind.name()))
+    }
+
+    /// Returns the glyph for notes of `kind`, if one is configured.
+    pub fn glyph_for_kind(&self, kind: &Kind) -> Option<&str> {
+        self.glyph(&format!("kind.{}", kind.name()))
+    }
+
+    /// Returns the style for remotes in the state named `state` (one of `STATES`).
+    pub fn style_for_state(&self, state: &str) -> Style {
+        self.style(&format!("state.{}", state))
+    }
+
+    /// Returns the glyph for remotes in the state named `state` (one of `STATES`),
+    /// if one is configured.
+    pub fn glyph_for_state(&self, state: &str) -> Option<&str> {
+        self.glyph(&format!("state.{}", state))
+    }
+
+    /// Returns `true` if a glyph is configured for any state. In that case, every
+    /// remote is drawn with a glyph (or a space, if its state has none) so that the
+    /// names stay put when the state changes.
+    pub fn has_state_glyphs(&self) -> bool {
+        self.glyphs.keys().any(|key| key.starts_with("state."))
+    }
+
+    /// Returns the style for `key`.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn style(&self, key: &str) -> Style {
+        self.styles.get(key).cloned().unwrap_or_else(Style::new)
+    }
+
+    /// Returns the glyph for `key`, if any.
+    ///
+    /// **This is an internal method and should not be called outside the impl.**
+    fn glyph(&self, key: &str) -> Option<&str> {
+        self.glyphs.get(key).map(|glyph| glyph.as_str())
+    }
+}
+
+// ----- parse_style ----------------------------------------------------------
+
+/// Parses `value` (see the module-level docs for the syntax) and returns the
+/// `Style`.
+fn parse_style(value: &str) -> Result<Style, Error> {
+    let mut rv = Style::new();
+    let mut words = value.split_whitespace();
+    while let Some(word) = words.next() {
+        rv = match word {
+            "plain" | "default" => rv,
+            "bold" => rv.bold(),
+            "dimmed" => rv.dimmed(),
+            "italic" => rv.italic(),
+            "underline" => rv.underline(),
+            "reverse" => rv.reverse(),
+            "on" => match words.next().and_then(parse_color) {
+                Some(color) => rv.on(color),
+                None => return Err(Error::new("expected a color after 'on'")),
+            },
+            _ => match parse_color(word) {
+                Some(color) => rv.fg(color),
+                None => {
+                    return Err(Error::new(&format!(
+                        "expected a color or modifier (got '{}')",
+                        word
+                    )))
+                }
+            },
+        };
+    }
+    Ok(rv)
+}
+
+// ----- parse_color ----------------------------------------------------------
+
+/// Parses `word` as a color name, number in the 256-color palette, or `#rrggbb`
+/// and returns the `Color`, or `None` if `word` is not a color.
+fn parse_color(word: &str) -> Option<Color> {
+    match word {
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "purple" | "magenta" => Some(Color::Purple),
+        "cyan" => Some(Color::Cyan),
+        "white" => Some(Color::White),
+        _ => {
+            if word.starts_with('#') && word.len() == 7 && word.is_ascii() {
+                let channel = |i: usize| u8::from_str_radix(&word[i..i + 2], 16).ok();
+                match (channel(1), channel(3), channel(5)) {
+                    (Some(r), Some(g), Some(b)) => Some(Color::RGB(r, g, b)),
+                    _ => None,
+                }
+            } else {
+                word.parse::<u8>().ok().map(Color::Fixed)
+            }
+        }
+    }
+}
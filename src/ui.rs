@@ -1,9 +1,14 @@
 //! Common UI components.
-use std::iter::Iterator;
+use std::{
+    fs,
+    iter::Iterator,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use git2::{Branch, BranchType, Oid, Repository};
+use termion;
 
-use app::Error;
+use app::{Error, Invocation};
 
 // ----- Kind -----------------------------------------------------------------
 
@@ -20,6 +25,19 @@ pub enum Kind {
     Failure,
 }
 
+impl Kind {
+    /// Returns the name of the kind (`"none"`, `"success"`, `"warning"` or
+    /// `"failure"`), for use in machine-readable output.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::None => "none",
+            Kind::Success => "success",
+            Kind::Warning => "warning",
+            Kind::Failure => "failure",
+        }
+    }
+}
+
 // ----- Note -----------------------------------------------------------------
 
 /// Represents an item in a `Summary`.
@@ -29,8 +47,14 @@ pub struct Note {
     group: usize,
     /// `Kind` of note.
     kind: Kind,
-    /// Message for the end user.
+    /// Key for the note, if it describes a value (e.g. a setting) rather than an
+    /// event or condition.
+    key: Option<String>,
+    /// Message for the end user. For notes with a key, this is the value.
     message: String,
+    /// Additional lines of detail for the message (e.g. the paths of files being
+    /// counted in the message).
+    details: Vec<String>,
 }
 
 impl Note {
@@ -39,7 +63,31 @@ impl Note {
         Self {
             group,
             kind,
+            key: None,
             message: message.to_owned(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Sets the key for this note, returning the note.
+    pub fn with_key(self, key: &str) -> Self {
+        Self {
+            group: self.group,
+            kind: self.kind,
+            key: Some(key.to_owned()),
+            message: self.message,
+            details: self.details,
+        }
+    }
+
+    /// Sets the lines of detail for this note, returning the note.
+    pub fn with_details(self, details: Vec<String>) -> Self {
+        Self {
+            group: self.group,
+            kind: self.kind,
+            key: self.key,
+            message: self.message,
+            details,
         }
     }
 
@@ -53,10 +101,24 @@ impl Note {
         &self.kind
     }
 
+    /// Returns the key for this note, if any.
+    #[allow(clippy::match_as_ref)]
+    pub fn key(&self) -> Option<&str> {
+        match self.key {
+            Some(ref key) => Some(key),
+            None => None,
+        }
+    }
+
     /// Returns the message for this note.
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Returns the lines of detail for this note.
+    pub fn details(&self) -> &[String] {
+        &self.details
+    }
 }
 
 // ----- Iter -----------------------------------------------------------------
@@ -98,6 +160,7 @@ impl<'a> Iterator for Iter<'a> {
 // ----- Summary --------------------------------------------------------------
 
 /// Represents a summary of current status or the results of an operation.
+#[derive(Clone)]
 pub struct Summary {
     /// Vec of notes comprising the summary.
     notes: Vec<Note>,
@@ -149,6 +212,356 @@ impl Summary {
     }
 }
 
+// ----- format_duration ------------------------------------------------------
+
+/// Returns a short, human-friendly description of a duration of `seconds` (e.g.
+/// `"1 minute"`, `"12 days"`).
+///
+/// Only the largest whole unit is used, so the result is an approximation. Months
+/// are considered to be 30 days and years 365 days.
+pub fn format_duration(seconds: u64) -> String {
+    let units = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+    for &(size, unit) in &units {
+        if seconds >= size {
+            let count = seconds / size;
+            let s = if count == 1 { "" } else { "s" };
+            return format!("{} {}{}", count, unit, s);
+        }
+    }
+    String::from("0 seconds")
+}
+
+// ----- format_timestamp -----------------------------------------------------
+
+/// Returns `time` formatted as an RFC 3339 timestamp in UTC, with millisecond
+/// precision (e.g. `"2018-12-14T21:09:58.512Z"`).
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    let seconds = since_epoch.as_secs();
+    let millis = since_epoch.subsec_millis();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Convert days since the epoch to a (proleptic Gregorian) date. This is Howard
+    // Hinnant's `civil_from_days` algorithm, restricted to dates after the epoch.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis
+    )
+}
+
+// ----- concurrent -----------------------------------------------------------
+
+/// Returns the number of concurrent jobs given in the argument `arg` of
+/// `invocation`, which must have a value. Exits with an error (see
+/// `Control::fatal()`) if it is not a number of one or greater.
+pub fn concurrent(invocation: &Invocation, arg: &str) -> u8 {
+    let concurrent_str = invocation
+        .value_of(arg)
+        .unwrap_or_else(|| panic!("expected {} to have an argument", arg));
+    let concurrent = match concurrent_str.parse::<u8>() {
+        Ok(concurrent) => concurrent,
+        Err(e) => {
+            invocation.control().fatal(&format!(
+                "failed to interpret value '{}' for {} ({})",
+                concurrent_str, arg, e
+            ));
+            // `fatal()` exits the process, so this is never used.
+            return 1;
+        }
+    };
+    if concurrent < 1 {
+        invocation.control().fatal(&format!(
+            "{} must be one or greater (got '{}')",
+            arg, concurrent
+        ));
+    }
+    concurrent
+}
+
+// ----- ellipsis -------------------------------------------------------------
+
+/// Returns the marker for truncated text: `…`, or `...` if `ascii` is `true` (the
+/// same as `to_ascii()` gives for `…`, so ASCII output uses one marker throughout).
+pub fn ellipsis(ascii: bool) -> &'static str {
+    if ascii {
+        "..."
+    } else {
+        "\u{2026}"
+    }
+}
+
+// ----- to_ascii -------------------------------------------------------------
+
+/// Returns `s` with the non-ASCII characters mgit draws with (arrows, bullets,
+/// box drawing and so on) replaced by ASCII equivalents. Other characters are
+/// left alone.
+pub fn to_ascii(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\u{2022}' => rv.push('*'),
+            '\u{2026}' => rv.push_str("..."),
+            '\u{2191}' => rv.push('^'),
+            '\u{2192}' => rv.push_str("->"),
+            '\u{2193}' => rv.push('v'),
+            '\u{2500}' => rv.push('-'),
+            '\u{2516}' => rv.push('`'),
+            '\u{2520}' => rv.push('|'),
+            '\u{2713}' => rv.push('+'),
+            '\u{2717}' => rv.push('x'),
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+// ----- to_ascii_glyph -------------------------------------------------------
+
+/// Returns `glyph` (from the theme) as ASCII: what `to_ascii()` gives for it, or
+/// `*` if that is not all ASCII (e.g. for a glyph `to_ascii()` doesn't know).
+pub fn to_ascii_glyph(glyph: &str) -> String {
+    let rv = to_ascii(glyph);
+    if rv.is_ascii() {
+        rv
+    } else {
+        String::from("*")
+    }
+}
+
+// ----- json_string ----------------------------------------------------------
+
+/// Returns `s` as a (quoted and escaped) JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len() + 2);
+    rv.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => rv.push_str("\\\""),
+            '\\' => rv.push_str("\\\\"),
+            '\n' => rv.push_str("\\n"),
+            '\r' => rv.push_str("\\r"),
+            '\t' => rv.push_str("\\t"),
+            c if (c as u32) < 0x20 => rv.push_str(&format!("\\u{:04x}", c as u32)),
+            c => rv.push(c),
+        }
+    }
+    rv.push('"');
+    rv
+}
+
+// ----- parse_duration -------------------------------------------------------
+
+/// Parses a duration given as one or more numbers, each followed by a unit (`s`,
+/// `m`, `h`, `d` or `w`; e.g. `"30m"`, `"2w"`, `"1h30m"`), and returns it in
+/// seconds.
+pub fn parse_duration(s: &str) -> Result<u64, Error> {
+    let error = || Error::new("expected a number followed by s, m, h, d or w, e.g. 30m or 2w");
+    let mut rv: u64 = 0;
+    let mut number = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let size = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(error()),
+        };
+        let count = number.parse::<u64>().map_err(|_| error())?;
+        rv = count
+            .checked_mul(size)
+            .and_then(|seconds| rv.checked_add(seconds))
+            .ok_or_else(error)?;
+        number.clear();
+    }
+    // A trailing number without a unit, or no numbers at all, is an error.
+    if !number.is_empty() || s.trim().is_empty() {
+        return Err(error());
+    }
+    Ok(rv)
+}
+
+// ----- last_fetched ---------------------------------------------------------
+
+/// Prefixes of the messages of the reflog entries that git writes when fetching
+/// (see `last_fetched()`).
+const FETCH_REFLOG_PREFIXES: [&str; 3] = ["fetch", "pull", "clone"];
+
+/// Returns the time the remote named `remote` was last fetched in `git`, or `None`
+/// if there is no record of it ever being fetched.
+///
+/// Only reflog entries written by a fetch (or a pull or clone, which fetch too) of
+/// remote-tracking branches count, since pushing updates them as well. Fetching
+/// only writes to the reflogs of remote-tracking branches that actually changed, so
+/// the modification time of `FETCH_HEAD` is also taken into account if it was
+/// written by a fetch from the remote's URL.
+pub fn last_fetched(git: &Repository, remote: &str) -> Option<SystemTime> {
+    /// Normalizes `url` the same way git does before writing it to `FETCH_HEAD`.
+    fn normalize(url: &str) -> &str {
+        url.trim_end_matches('/').trim_end_matches(".git")
+    }
+
+    let mut rv = None;
+
+    if let Ok(references) = git.references_glob(&format!("refs/remotes/{}/*", remote)) {
+        for reference in references.filter_map(|reference| reference.ok()) {
+            let entry_time = reference
+                .name()
+                .and_then(|name| git.reflog(name).ok())
+                .and_then(|reflog| {
+                    // Entries are ordered from the most recent one.
+                    reflog
+                        .iter()
+                        .find(|entry| match entry.message() {
+                            Some(message) => FETCH_REFLOG_PREFIXES
+                                .iter()
+                                .any(|prefix| message.starts_with(prefix)),
+                            None => false,
+                        })
+                        .map(|entry| entry.committer().when().seconds())
+                });
+            if let Some(seconds) = entry_time {
+                if seconds > 0 {
+                    let time = UNIX_EPOCH + Duration::from_secs(seconds as u64);
+                    rv = rv.max(Some(time));
+                }
+            }
+        }
+    }
+
+    let url = git
+        .find_remote(remote)
+        .ok()
+        .and_then(|remote| remote.url().map(|url| normalize(url).to_owned()));
+    if let Some(url) = url {
+        let path = git.path().join("FETCH_HEAD");
+        if let Ok(contents) = fs::read_to_string(&path) {
+            // Each line ends with `... of <url>`.
+            let mentions_url = contents.lines().any(|line| match line.rfind(" of ") {
+                Some(i) => normalize(&line[i + 4..]) == url,
+                None => false,
+            });
+            if mentions_url {
+                if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                    rv = rv.max(Some(modified));
+                }
+            }
+        }
+    }
+
+    rv
+}
+
+// ----- Resize ---------------------------------------------------------------
+
+/// Number of milliseconds after which a terminal resize is considered "settled."
+const DEBOUNCE_MILLIS: u64 = 500;
+
+/// Result of checking the terminal size with `ResizeDebounce.check()`.
+pub enum Resize {
+    /// Terminal size is the same as the drawn size.
+    Unchanged,
+    /// Terminal size has changed, but the user may still be resizing.
+    Pending,
+    /// The UI needs to be (re)drawn with the width and height.
+    Draw(u16, u16),
+}
+
+// ----- ResizeDebounce -------------------------------------------------------
+
+/// Tracks the size of a full-screen UI and debounces terminal resizes.
+pub struct ResizeDebounce {
+    /// Width and height of the drawn UI.
+    drawn: (u16, u16),
+    /// Holds the terminal resize debounce state.
+    ///
+    /// Every call to `check()`, we check the terminal size. When terminal size changes
+    /// from the `drawn` size, this gets set to `Some(<new-width>, <new-height>,
+    /// Instant::now())`.
+    ///
+    /// Subsequent checks will continue to check terminal size. If it changes again, a
+    /// new `Some(w, h, Instant::now())` value is generated. If `DEBOUNCE_MILLIS` goes
+    /// by without a change, mgit assumes the user is done resizing and tells the
+    /// caller to redraw the UI based on the new terminal size. (Debounce is then set
+    /// to `None` as we are done debouncing.)
+    debounce: Option<(u16, u16, Instant)>,
+}
+
+impl ResizeDebounce {
+    /// Creates and returns a new `ResizeDebounce` instance.
+    pub fn new() -> Self {
+        Self {
+            drawn: (0, 0),
+            debounce: None,
+        }
+    }
+
+    /// Checks the terminal size against the drawn size, returning what (if anything)
+    /// the caller needs to do about it.
+    ///
+    /// If nothing has been drawn yet, this always returns `Resize::Draw`.
+    pub fn check(&mut self) -> Resize {
+        let (w, h) = termion::terminal_size().expect("failed to get terminal size");
+        let debounce = Some((w, h, Instant::now()));
+        let (drawn_w, drawn_h) = self.drawn;
+        if drawn_w == 0 && drawn_h == 0 {
+            Resize::Draw(w, h)
+        } else if let Some((new_w, new_h, t)) = self.debounce {
+            if w == new_w && h == new_h {
+                if t.elapsed() >= Duration::from_millis(DEBOUNCE_MILLIS) {
+                    self.debounce = None;
+                    Resize::Draw(w, h)
+                } else {
+                    Resize::Pending
+                }
+            } else {
+                self.debounce = debounce;
+                Resize::Pending
+            }
+        } else if w != drawn_w || h != drawn_h {
+            self.debounce = debounce;
+            Resize::Pending
+        } else {
+            Resize::Unchanged
+        }
+    }
+
+    /// Records that the UI has been drawn with width `w` and height `h`.
+    pub fn drawn(&mut self, w: u16, h: u16) {
+        self.drawn = (w, h);
+    }
+}
+
 // ----- TrackingBranch -------------------------------------------------------
 
 /// Convenience wrapper for a tracking branch.
@@ -0,0 +1,279 @@
+//! Minimal wrapper around the Linux inotify API, used to watch directories for
+//! changes.
+//!
+//! The version of libc mgit uses does not include bindings for inotify, so the
+//! handful of functions and constants mgit needs are declared here. On other
+//! platforms, `Watcher::new()` always returns an error.
+use std::path::{Path, PathBuf};
+
+use libc;
+
+use app::Error;
+
+// ----- ffi ------------------------------------------------------------------
+
+/// Declarations for the parts of the inotify API used by mgit.
+///
+/// See `inotify(7)` for the details.
+#[cfg(target_os = "linux")]
+mod ffi {
+    use libc::{c_char, c_int};
+
+    /// File was modified.
+    pub const IN_MODIFY: u32 = 0x0000_0002;
+    /// Metadata (permissions, timestamps, etc) changed.
+    pub const IN_ATTRIB: u32 = 0x0000_0004;
+    /// File opened for writing was closed.
+    pub const IN_CLOSE_WRITE: u32 = 0x0000_0008;
+    /// File was moved out of the watched directory.
+    pub const IN_MOVED_FROM: u32 = 0x0000_0040;
+    /// File was moved into the watched directory.
+    pub const IN_MOVED_TO: u32 = 0x0000_0080;
+    /// File or directory was created in the watched directory.
+    pub const IN_CREATE: u32 = 0x0000_0100;
+    /// File or directory was deleted from the watched directory.
+    pub const IN_DELETE: u32 = 0x0000_0200;
+    /// Watched directory was itself deleted.
+    pub const IN_DELETE_SELF: u32 = 0x0000_0400;
+    /// Watched directory was itself moved.
+    pub const IN_MOVE_SELF: u32 = 0x0000_0800;
+    /// Event queue overflowed, events were dropped.
+    pub const IN_Q_OVERFLOW: u32 = 0x0000_4000;
+    /// Watch was removed (explicitly, or because the directory was deleted).
+    pub const IN_IGNORED: u32 = 0x0000_8000;
+    /// Subject of the event is a directory.
+    pub const IN_ISDIR: u32 = 0x4000_0000;
+
+    /// Layout of the fixed-size part of an event, which is followed by `len` bytes
+    /// of (nul-padded) file name.
+    #[repr(C)]
+    pub struct inotify_event {
+        pub wd: c_int,
+        pub mask: u32,
+        pub cookie: u32,
+        pub len: u32,
+    }
+
+    extern "C" {
+        pub fn inotify_init1(flags: c_int) -> c_int;
+        pub fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+    }
+}
+
+/// Events mgit is interested in.
+#[cfg(target_os = "linux")]
+const MASK: u32 = ffi::IN_MODIFY
+    | ffi::IN_ATTRIB
+    | ffi::IN_CLOSE_WRITE
+    | ffi::IN_MOVED_FROM
+    | ffi::IN_MOVED_TO
+    | ffi::IN_CREATE
+    | ffi::IN_DELETE
+    | ffi::IN_DELETE_SELF
+    | ffi::IN_MOVE_SELF;
+
+// ----- Watch ----------------------------------------------------------------
+
+/// Identifies a watched directory (wraps the inotify "watch descriptor").
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Watch(i32);
+
+// ----- Event ----------------------------------------------------------------
+
+/// Represents a change to (something in) a watched directory.
+pub struct Event {
+    /// Directory in which the change happened.
+    watch: Watch,
+    /// Raw inotify event mask.
+    mask: u32,
+    /// Name of the file or directory that changed, relative to the watched
+    /// directory. `None` if the change was to the watched directory itself.
+    name: Option<PathBuf>,
+}
+
+impl Event {
+    /// Returns the watched directory in which the change happened.
+    pub fn watch(&self) -> Watch {
+        self.watch
+    }
+
+    /// Returns the name of the file or directory that changed, if any.
+    #[allow(clippy::match_as_ref)]
+    pub fn name(&self) -> Option<&Path> {
+        match self.name {
+            Some(ref name) => Some(name),
+            None => None,
+        }
+    }
+
+    /// Returns `true` if this event represents a new directory inside the watched
+    /// directory (which will need to be watched separately).
+    #[cfg(target_os = "linux")]
+    pub fn is_new_dir(&self) -> bool {
+        self.mask & ffi::IN_ISDIR != 0 && self.mask & (ffi::IN_CREATE | ffi::IN_MOVED_TO) != 0
+    }
+
+    /// Returns `true` if the watch has been removed (e.g. the directory was deleted)
+    /// and will not yield further events.
+    #[cfg(target_os = "linux")]
+    pub fn is_removed(&self) -> bool {
+        self.mask & ffi::IN_IGNORED != 0
+    }
+
+    /// Returns `true` if the kernel's event queue overflowed. Some events have been
+    /// lost, so the caller should assume everything has changed.
+    #[cfg(target_os = "linux")]
+    pub fn is_overflow(&self) -> bool {
+        self.mask & ffi::IN_Q_OVERFLOW != 0
+    }
+
+    /// Always `false` on this platform.
+    #[cfg(not(target_os = "linux"))]
+    pub fn is_new_dir(&self) -> bool {
+        false
+    }
+
+    /// Always `false` on this platform.
+    #[cfg(not(target_os = "linux"))]
+    pub fn is_removed(&self) -> bool {
+        false
+    }
+
+    /// Always `false` on this platform.
+    #[cfg(not(target_os = "linux"))]
+    pub fn is_overflow(&self) -> bool {
+        false
+    }
+}
+
+// ----- Watcher --------------------------------------------------------------
+
+/// Watches directories (non-recursively) for changes.
+pub struct Watcher {
+    /// File descriptor for the inotify instance.
+    fd: i32,
+}
+
+impl Watcher {
+    /// Creates and returns a new `Watcher` instance.
+    #[cfg(target_os = "linux")]
+    pub fn new() -> Result<Self, Error> {
+        use std::io;
+
+        let fd = unsafe { ffi::inotify_init1(libc::O_NONBLOCK | libc::O_CLOEXEC) };
+        if fd < 0 {
+            Err(Error::new(&format!(
+                "failed to initialize inotify ({})",
+                io::Error::last_os_error()
+            )))
+        } else {
+            Ok(Self { fd })
+        }
+    }
+
+    /// Watching for changes is not supported on this platform, so this always
+    /// returns an error.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new() -> Result<Self, Error> {
+        Err(Error::new(
+            "watching for changes is only supported on linux",
+        ))
+    }
+
+    /// Starts watching the directory at `path`, returning the `Watch` that will be
+    /// used to identify events for the directory.
+    #[cfg(target_os = "linux")]
+    pub fn add(&self, path: &Path) -> Result<Watch, Error> {
+        use std::{ffi::CString, io, os::unix::ffi::OsStrExt};
+
+        let c_path = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(c_path) => c_path,
+            Err(_) => {
+                return Err(Error::new(&format!(
+                    "path {} contains a nul byte",
+                    path.display()
+                )))
+            }
+        };
+        let wd = unsafe { ffi::inotify_add_watch(self.fd, c_path.as_ptr(), MASK) };
+        if wd < 0 {
+            Err(Error::new(&format!(
+                "failed to watch {} ({})",
+                path.display(),
+                io::Error::last_os_error()
+            )))
+        } else {
+            Ok(Watch(wd))
+        }
+    }
+
+    /// Watching for changes is not supported on this platform, so this always
+    /// returns an error.
+    #[cfg(not(target_os = "linux"))]
+    pub fn add(&self, _: &Path) -> Result<Watch, Error> {
+        Err(Error::new(
+            "watching for changes is only supported on linux",
+        ))
+    }
+
+    /// Returns all events that have happened since the last call to `read()`.
+    ///
+    /// This does not block; if nothing has happened, an empty vec is returned.
+    #[cfg(target_os = "linux")]
+    pub fn read(&self) -> Vec<Event> {
+        use std::{ffi::OsStr, mem, os::unix::ffi::OsStrExt, ptr};
+
+        let header = mem::size_of::<ffi::inotify_event>();
+        let mut rv = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n =
+                unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n <= 0 {
+                // The descriptor is non-blocking, so this is (almost certainly) `EAGAIN`,
+                // meaning there are no more events to read.
+                break;
+            }
+            let n = n as usize;
+            let mut offset = 0;
+            while offset + header <= n {
+                #[allow(clippy::cast_ptr_alignment)]
+                let event = unsafe {
+                    ptr::read_unaligned(buf[offset..].as_ptr() as *const ffi::inotify_event)
+                };
+                let start = offset + header;
+                let end = start + event.len as usize;
+                let name = if event.len > 0 {
+                    let bytes = &buf[start..end];
+                    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                    Some(PathBuf::from(OsStr::from_bytes(&bytes[..len])))
+                } else {
+                    None
+                };
+                rv.push(Event {
+                    watch: Watch(event.wd),
+                    mask: event.mask,
+                    name,
+                });
+                offset = end;
+            }
+        }
+        rv
+    }
+
+    /// Watching for changes is not supported on this platform, so this always
+    /// returns an empty vec.
+    #[cfg(not(target_os = "linux"))]
+    pub fn read(&self) -> Vec<Event> {
+        Vec::new()
+    }
+}
+
+impl Drop for Watcher {
+    /// Closes the inotify file descriptor.
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}